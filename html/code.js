@@ -18,10 +18,16 @@ var PlayerType = {
 
 var REFRESH_TIMEOUT = 250;
 var uid = "";
-var last_move_no = -1;
+var current = null;     // the board shown
+var refreshing = false;
 var player_white = PlayerType.HUMAN;
 var player_black = PlayerType.AI;
 
+// The position which is set up on the analysis board, see edit_position.
+var editing = null;
+var editing_next_move = "Black";
+var EDIT_CYCLE = [Color.EMPTY, Color.WHITE_NORMAL, Color.WHITE_DAME, Color.BLACK_NORMAL, Color.BLACK_DAME];
+
 
 main();
 
@@ -90,17 +96,21 @@ function update_player(data) {
 }
 
 function refresh() {
+  refreshing = true;
   $.get("/rest/get/" + uid, function(data) {
-    if (data.move_no && data.move_no != last_move_no) {  // if something changed
+    if (data.board && editing) {  // shown when the editing stops
+      current = data;
+      setTimeout(refresh, REFRESH_TIMEOUT);
+    } else if (data.board) {  // each new board is queued once, also one which has been set up
       hide_spinning();
       hide_status();
-      last_move_no = data.move_no;
+      $("#winner").hide();
+      current = data;
       animations = data.last_moves;
       do_animation(data, 0, function () {
-        if (data.winner != "None") {  // if a player has won display the message
-          winner(data.winner);
-        } else {                      // otherwise continue with the game
-          update_player(data);
+        if (show_result(data)) {
+          refreshing = false;
+        } else {
           setTimeout(refresh, REFRESH_TIMEOUT);
         }
       });
@@ -110,6 +120,19 @@ function refresh() {
   }).fail(error);
 }
 
+// Shows the result of a finished game or whose turn it is. Returns true if the game is finished.
+function show_result(data) {
+  if (data.winner != "None") {  // if a player has won display the message
+    winner(data.winner);
+  } else if (data.finished) {   // otherwise the game is a draw
+    draw(data.outcome.fields[0]);
+  } else {                      // otherwise continue with the game
+    update_player(data);
+    return false;
+  }
+  return true;
+}
+
 function clear_all() {
   $(".selected").removeClass("selected");
   $(".possible").remove();
@@ -126,6 +149,11 @@ function winner(p) {
   $("#winner").show();
 }
 
+function draw(reason) {
+  $("#winnertxt").html(reason == "Repetition" ? "Draw by repetition!" : "Draw by the move rule!");
+  $("#winner").show();
+}
+
 function move_piece(i, x, y, dx, dy) {
 
   $("#" + i).animate(
@@ -133,12 +161,22 @@ function move_piece(i, x, y, dx, dy) {
       clear_all();
       $.post("/rest/move/" + uid + "/" + x + "/" + y + "/" + dx + "/" + dy, function(data) {
       })
-      .fail(error);
+      .fail(function(xhr) {
+        if (xhr.responseJSON && xhr.responseJSON.error) {  // the move was rejected
+          status(xhr.responseJSON.error);
+        } else {
+          error();
+        }
+      });
     }
   );
 }
 
 function select_piece(i, x, y) {
+  if (editing) {
+    edit_square(x, y);
+    return;
+  }
   var p = $("#boxboard").offset();
   clear_all();
   $.get("/rest/select/" + uid + "/" + x + "/" + y, function(data) {
@@ -215,7 +253,7 @@ function start_game() {
   $(".ask").hide();
   $.post("/rest/new/" + player_white + "/" + player_black, function(data) {
     uid = data.uid;
-    last_move_no = data.move_no;
+    current = data;
     player_black = data.player_black;
     player_white = data.player_white;
     update_player(data);
@@ -225,9 +263,93 @@ function start_game() {
   .fail(error);
 }
 
+// Analysis board: the pieces are changed by clicking on the squares, each click puts the next
+// piece of EDIT_CYCLE on the square. send_position replaces the board of the game by the
+// position, the server answers with the problems found if the position is not valid.
+function edit_position() {
+  if (!current || editing) {
+    return;
+  }
+  editing = current.board.slice();
+  editing_next_move = current.next_move;
+  clear_all();
+  clear_board();
+  draw_board(editing);
+  $("#setup_edit").hide();
+  $(".setup_active").show();
+  $("#setup_next_move").html(editing_next_move + " to move");
+  status("Click on the squares to change the pieces.");
+}
+
+function edit_square(x, y) {
+  if ((x + y) % 2 != 0) {
+    return;
+  }
+  var i = y * 8 + x;
+  editing[i] = EDIT_CYCLE[(EDIT_CYCLE.indexOf(editing[i]) + 1) % EDIT_CYCLE.length];
+  clear_board();
+  draw_board(editing);
+}
+
+// Clicks on empty squares; pieces have their own handler, see add_piece.
+function click_board(e) {
+  if (!editing) {
+    return;
+  }
+  var p = $("#boxboard").offset();
+  var x = Math.floor((e.pageX - p.left - 46) / 63.4);
+  var y = 7 - Math.floor((e.pageY - p.top - 51) / 63.2);
+  if (x >= 0 && x < 8 && y >= 0 && y < 8) {
+    edit_square(x, y);
+  }
+}
+
+function toggle_next_move() {
+  editing_next_move = editing_next_move == "Black" ? "White" : "Black";
+  $("#setup_next_move").html(editing_next_move + " to move");
+}
+
+function stop_editing() {
+  editing = null;
+  $(".setup_active").hide();
+  $("#setup_edit").show();
+}
+
+function cancel_position() {
+  stop_editing();
+  clear_board();
+  draw_board(current.board);
+  hide_status();
+  show_result(current);
+}
+
+function send_position() {
+  $.ajax({
+    url: "/rest/setup/" + uid,
+    type: "POST",
+    contentType: "application/json",
+    data: JSON.stringify({ board: editing, next_move: editing_next_move }),
+  })
+  .done(function(data) {
+    stop_editing();
+    if (!refreshing) {  // the game was over, show the new board
+      refresh();
+    }
+  })
+  .fail(function(xhr) {
+    if (xhr.responseJSON && xhr.responseJSON.error) {  // the position was rejected
+      status(xhr.responseJSON.error);
+    } else {
+      error();
+    }
+  });
+}
+
 function main() {
   $("#error").hide();
   $("#winner").hide();
+  $(".setup_active").hide();
+  $("#box").click(click_board);
   hide_spinning();
   hide_status();
 }
@@ -1,15 +1,21 @@
 #!/usr/bin/env python3
 
-import argparse, uuid, json, threading, multiprocessing, time, collections
-from flask import Flask, jsonify
+import argparse, uuid, json, threading, multiprocessing, time, collections, sys
+from flask import Flask, jsonify, request
 
 import engine
 
 parser = argparse.ArgumentParser(description = "")
 parser.add_argument("--port", type = int, default = 5002, help = "port")
 parser.add_argument("--load", type = str, help = "load board setting from file")
+parser.add_argument("--fen", type = str, help = "start the first game from this position, e.g. B:WK22,K24:BK6,K8")
 parser.add_argument("--depth", type = int, default = 7, help = "maximum search depth")
+parser.add_argument("--time", type = int, help = "time in ms to think per move; overrides --depth")
+parser.add_argument("--threads", type = int, default = 1, help = "number of threads per search")
+parser.add_argument("--ponder", action = "store_true", help = "think on the human's time; requires --time")
 args = parser.parse_args()
+if args.ponder and args.time == None:
+    parser.error("--ponder requires --time")
 
 app = Flask(__name__)
 
@@ -19,7 +25,18 @@ colors = {}
 next_uid = None
 board_queues = {}
 
-ai_minimax = lambda q, uid: q.put((uid, engine.ai_minimax(boards[uid], args.depth)))
+def ai_minimax(q, uid):
+    if args.time != None:
+        q.put((uid, engine.ai_minimax_time(boards[uid], args.time, args.threads)))
+    else:
+        q.put((uid, engine.ai_minimax(boards[uid], args.depth, args.threads)))
+
+# runs in a thread of this process, because the search on the human's time is kept by the engine
+def ai_ponder(q, uid):
+    q.put((uid, engine.ai_ponder(uid, boards[uid], args.time, args.threads)))
+
+def pondering(uid):
+    return args.ponder and "human" in colors[uid]
 
 def update_board(uid, b, player):
     boards[uid] = b
@@ -40,17 +57,23 @@ def queue_add(uid, board):
 
 def start_ai(uid):
     b = json.loads(boards[uid])
-    if b["winner"] != "None":
+    if b.get("finished", b["winner"] != "None"):
+        if pondering(uid):
+            engine.ponder_stop(uid)
         return
-    if b["next_move"].upper() == "WHITE" and colors[uid][0] == "ai":
-        multiprocessing.Process(target = ai_minimax, args = (q, uid)).start()
-    if b["next_move"].upper() == "BLACK" and colors[uid][1] == "ai":
-        multiprocessing.Process(target = ai_minimax, args = (q, uid)).start()
-    # TODO: duplicated code
+    if (b["next_move"].upper() == "WHITE" and colors[uid][0] == "ai") or \
+       (b["next_move"].upper() == "BLACK" and colors[uid][1] == "ai"):
+        if pondering(uid):
+            threading.Thread(target = ai_ponder, args = (q, uid)).start()
+        else:
+            multiprocessing.Process(target = ai_minimax, args = (q, uid)).start()
 
 def nn_game(player_white, player_black):
     global next_uid
     b = engine.new_game()
+    if args.fen != None: # use position from the command line
+        b = engine.from_fen(args.fen)
+        args.fen = None
     if next_uid != None: # use configuration from file
         uid = next_uid
         next_uid = None
@@ -88,15 +111,46 @@ def select(uid, x, y):
 @app.route("/rest/move/<string:uid>/<int:x>/<int:y>/<int:dx>/<int:dy>", methods = ["POST"])
 def move(uid, x, y, dx, dy):
     if uid in boards:
-        data = update_board(uid, engine.move_it(boards[uid], x, y, dx, dy), "HUMAN")
+        try:
+            b = engine.move_it(boards[uid], x, y, dx, dy)
+        except ValueError as e:
+            return jsonify({ "error": str(e) }), 400
+        if pondering(uid):
+            engine.ponder_move(uid, b)
+        data = update_board(uid, b, "HUMAN")
+        queue_add(uid, boards[uid])
+        start_ai(uid)
+        return jsonify(data)
+
+# analysis board: replaces the board of a game by a position which has been set up, see
+# engine.setup_position for the format
+@app.route("/rest/setup/<string:uid>", methods = ["POST"])
+def setup(uid):
+    if uid in boards:
+        try:
+            b = engine.setup_position(request.get_data(as_text = True))
+        except ValueError as e:
+            return jsonify({ "error": str(e) }), 400
+        if pondering(uid):
+            engine.ponder_stop(uid)
+        data = update_board(uid, b, "SETUP")
         queue_add(uid, boards[uid])
         start_ai(uid)
         return jsonify(data)
 
+@app.route("/rest/breakdown/<string:uid>/<int:depth>", methods = ["GET"])
+def breakdown(uid, depth):
+    if uid in boards:
+        return jsonify(json.loads(engine.ai_breakdown(boards[uid], depth, args.threads)))
+
 if __name__ == "__main__":
     if args.load != None:
         print("Starting with given configuration.")
         data = json.loads(open(args.load).read())
+        try:
+            engine.to_fen(data["board"])
+        except ValueError as e:
+            sys.exit("%s: %s" % (args.load, e))
         uid = data["uid"]
         colors[uid] = [data["player_white"], data["player_black"]]
         update_board(uid, data["board"], "LOADED")
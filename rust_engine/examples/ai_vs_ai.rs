@@ -1,37 +1,95 @@
 extern crate engine;
 
-use engine::board::board::Board;
+use std::env;
+
+use engine::ai;
 use engine::ai::minimax::MiniMax;
-use engine::dame::Dame;
+use engine::board::board::{Board, DrawReason, GameOutcome, Player};
+use engine::board::history::Positions;
+use engine::dame::{Dame, DameMove};
+use engine::eval::{WeightedEvaluator, Weights};
 
-fn main() {
-    println!("running ...");
-/*
-    let b = Board::new();
-    let p = b.player();
-
-    let mut d = Dame {
-        b: b,
-        ai: p  // TODO: why do we need this  - I think for computing scores
-    };
-
-    while !d.b.finished() {
-
-        let p = d.b.player();
-        d.ai = d.b.player();
-
-        while !d.b.finished() && d.b.player() == p {
-            let mut x = MiniMax::new(5);
-            let m = x.minimax(&d);
-            println!("scores computed: {}", x.scores());
-            println!("winning score: {}", x.score());
-            println!("time in ms: {}", x.duration_ms());
-            d.b.move_it(m.src_x, m.src_y, m.dst_x, m.dst_y);
+// Plays a match between two evaluation functions.
+//
+// usage: ai_vs_ai [weights A] [weights B] [games] [depth]
+//
+// The weights are JSON files as read by Weights::load; "-" selects the material weights of
+// Weights::new and "positional" the weights of Weights::positional which Dame::new uses. The
+// engines change colors after each game and the first moves are played at random, so that
+// not all games are the same. The games are played to the end; the move rule of Board and the
+// repetitions kept by Positions end drawn games.
+
+const RANDOM_MOVES: usize = 4;
+
+fn weights(arg: Option<String>) -> Weights {
+    match arg {
+        Some(ref p) if p == "positional" => Weights::positional(),
+        Some(ref p) if p != "-" => Weights::load(p).unwrap_or_else(|e| panic!("{}", e)),
+        _ => Weights::new()
+    }
+}
+
+fn search(depth: usize) -> MiniMax<DameMove> {
+    let mut x = MiniMax::new(depth);
+    x.set_alpha_beta(true);
+    x.set_quiescence(true);
+    x.set_move_ordering(true);
+    x.set_table_size(16);
+    x
+}
+
+// Plays one game to the end and returns its outcome.
+fn play(white: &Weights, black: &Weights, depth: usize) -> GameOutcome {
+    let mut b = Board::new();
+    let mut n = 0;
+    while !b.finished() && n < RANDOM_MOVES {
+        let p = b.player();
+        ai::random(&mut b);
+        if b.player() != p {
+            n += 1;
         }
+    }
+
+    let mut positions = Positions::new(&b);
+    while !b.finished() {
+        let w = if b.player() == Player::White { white } else { black };
+        let mut d = Dame::with_evaluator(b, WeightedEvaluator::new(*w));
+        d.positions = positions.clone();
+        let m = match search(depth).minimax(d) {
+            Some(m) => m,
+            None => break
+        };
+        b.make_move(&m);
+        positions.push(&mut b);
+    }
+    b.outcome()
+}
 
-        println!("moves: {:?}", d.b.get_last_moves());
-        d.b.clear_last_moves();
+fn main() {
+    let mut args = env::args().skip(1);
+    let a = weights(args.next());
+    let b = weights(args.next());
+    let games: usize = args.next().map(|s| s.parse().unwrap()).unwrap_or(10);
+    let depth: usize = args.next().map(|s| s.parse().unwrap()).unwrap_or(5);
+
+    println!("A: {}", a.to_json());
+    println!("B: {}", b.to_json());
+
+    let (mut wins_a, mut wins_b, mut repetitions, mut move_rule) = (0, 0, 0, 0);
+    for i in 0..games {
+        let (white, black) = if i % 2 == 0 { (&a, &b) } else { (&b, &a) };
+        let o = play(white, black, depth);
+        let a_is_white = i % 2 == 0;
+        match o {
+            GameOutcome::WhiteWins if a_is_white => wins_a += 1,
+            GameOutcome::BlackWins if !a_is_white => wins_a += 1,
+            GameOutcome::WhiteWins | GameOutcome::BlackWins => wins_b += 1,
+            GameOutcome::Draw(DrawReason::Repetition) => repetitions += 1,
+            GameOutcome::Draw(DrawReason::MoveRule) => move_rule += 1,
+            GameOutcome::Ongoing => unreachable!()
+        }
+        println!("game {:3}: A plays {}, {:?}", i + 1, if a_is_white { "white" } else { "black" }, o);
     }
-*/
-    println!("done");
-}
\ No newline at end of file
+    println!("A wins: {}, B wins: {}, draws by repetition: {}, draws by the move rule: {}",
+             wins_a, wins_b, repetitions, move_rule);
+}
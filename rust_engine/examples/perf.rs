@@ -1,26 +1,89 @@
 extern crate engine;
+extern crate time;
 
-use std::iter::repeat;
+use time::PreciseTime;
 
-use engine::board::board::{Board, Color};
+use std::mem::size_of;
+
+use engine::board::board::{Board, Undo};
 use engine::ai::minimax::MiniMax;
-use engine::dame::Dame;
-
-fn perf() {
-    let mut f: Vec<Color> = repeat(Color::Empty).take(8 * 8).collect();
-    f[2 * 8 + 2] = Color::WhiteDame;
-    f[2 * 8 + 6] = Color::WhiteDame;
-    f[6 * 8 + 2] = Color::BlackDame;
-    f[6 * 8 + 6] = Color::BlackDame;
-    let mut x = MiniMax::new(5);
-    x.minimax(Dame::new(Board::from(f)));
+use engine::dame::{Dame, DameMove};
+
+fn kings() -> Board {
+    Board::from_fen("B:WK22,K24:BK6,K8").unwrap()
+}
+
+fn perf(name: &str, b: Board, mut x: MiniMax<DameMove>) {
+    x.minimax(Dame::new(b));
+    println!("{}", name);
     println!("* scores computed: {}", x.scores());
+    println!("* cutoffs        : {}", x.cutoffs());
+    println!("* first move cuts: {} / {}", x.first_move_cutoffs(), x.cutoff_nodes());
+    println!("* table hits     : {} / {}", x.table_hits(), x.table_hits() + x.table_misses());
     println!("* winning score  : {}", x.score());
     println!("* time           : {}", x.duration_ms());
 }
 
+fn search(depth: usize, alpha_beta: bool, table: usize, ordering: bool, threads: usize) -> MiniMax<DameMove> {
+    let mut x = MiniMax::new(depth);
+    x.set_alpha_beta(alpha_beta);
+    x.set_table_size(table);
+    x.set_move_ordering(ordering);
+    x.set_threads(threads);
+    x
+}
+
+// Counts the positions reachable with the given number of moves. Each position is copied
+// before a move is made, as Dame::set does in the search.
+fn walk(b: &Board, depth: usize) -> usize {
+    if depth == 0 || b.finished() {
+        return 1;
+    }
+    b.moves().iter().map(|m| {
+        let mut c = *b;
+        c.make_move(m);
+        walk(&c, depth - 1)
+    }).sum()
+}
+
+// Counts the same positions as walk but makes the moves on the board itself and takes them back
+// with Board::unmake, as the search does with make/unmake.
+fn walk_unmake(b: &mut Board, depth: usize) -> usize {
+    if depth == 0 || b.finished() {
+        return 1;
+    }
+    b.moves().iter().map(|m| {
+        let u = b.make(m).unwrap();
+        let n = walk_unmake(b, depth - 1);
+        b.unmake(u);
+        n
+    }).sum()
+}
+
 fn main() {
     println!("measuring performance ...");
-    perf();
+    let t = PreciseTime::now();
+    let n = walk(&Board::new(), 9);
+    println!("move generation, start, depth 9");
+    println!("* positions      : {}", n);
+    println!("* time           : {}", t.to(PreciseTime::now()).num_milliseconds());
+    let t = PreciseTime::now();
+    let n = walk_unmake(&mut Board::new(), 9);
+    println!("move generation, start, depth 9, make/unmake");
+    println!("* positions      : {}", n);
+    println!("* time           : {}", t.to(PreciseTime::now()).num_milliseconds());
+    println!("* bytes per move : {} instead of {} for a copy", size_of::<Undo>(), size_of::<Board>());
+    perf("four kings, depth 5, minimax", kings(), search(5, false, 0, false, 1));
+    perf("four kings, depth 5, alpha-beta", kings(), search(5, true, 0, false, 1));
+    let configs = [(9, 0, false, 1), (9, 16, false, 1), (9, 16, true, 1), (9, 16, true, 4)];
+    for &(depth, table, ordering, threads) in configs.iter() {
+        let name = format!("depth {}, alpha-beta, table {} MB, ordering {}, threads {}", depth, table, ordering, threads);
+        perf(&format!("four kings, {}", name), kings(), search(depth, true, table, ordering, threads));
+        perf(&format!("start, {}", name), Board::new(), search(depth, true, table, ordering, threads));
+    }
+    for &make_unmake in [false, true].iter() {
+        let mut x = search(9, true, 16, true, 1);
+        x.set_make_unmake(make_unmake);
+        perf(&format!("start, depth 9, alpha-beta, table 16 MB, ordering true, make/unmake {}", make_unmake), Board::new(), x);
+    }
 }
-
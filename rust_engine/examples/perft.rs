@@ -0,0 +1,43 @@
+extern crate engine;
+extern crate time;
+
+use std::env;
+
+use time::PreciseTime;
+
+use engine::board::board::Board;
+use engine::board::notation::numeric;
+use engine::board::perft::{divide, perft};
+
+// Counts the positions reachable from a position, see board::perft.
+//
+// usage: perft [depth] [FEN]
+//
+// Prints the number of positions for each move at the given depth (divide), so that a wrong
+// count can be narrowed down by comparing with another move generator, and the numbers for
+// all smaller depths. Without a FEN the start position is used.
+
+const DEFAULT_DEPTH: usize = 7;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let depth = args.next().map_or(DEFAULT_DEPTH, |d| d.parse().unwrap_or_else(|_| panic!("invalid depth: {}", d)));
+    let b = match args.next() {
+        Some(fen) => Board::from_fen(&fen).unwrap_or_else(|e| panic!("{}", e)),
+        None => Board::new()
+    };
+    println!("{}\n", b);
+
+    let mut total = 0;
+    for (m, n) in divide(&b, depth) {
+        println!("{:<12} {}", numeric(&m), n);
+        total += n;
+    }
+    println!("\n{:<12} {}\n", "total", total);
+
+    for d in 1..depth + 1 {
+        let t = PreciseTime::now();
+        let n = perft(&b, d);
+        println!("depth {:>2}: {:>12} positions in {} ms", d, n, t.to(PreciseTime::now()).num_milliseconds());
+    }
+}
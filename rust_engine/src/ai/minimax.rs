@@ -1,6 +1,14 @@
+use futures::Future;
+use futures_cpupool::CpuPool;
 use rand::{Rng, thread_rng};
 use time::PreciseTime;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
+use std::mem::size_of;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 #[derive(Debug, Clone, Copy)]
 pub enum Player {
@@ -9,18 +17,40 @@ pub enum Player {
 }
 
 pub trait Game<Move>
-    where Move: Clone + Copy + Debug
+    where Move: Clone + Copy + Debug + Eq + Hash
 {
-    fn valid_moves(&self)    -> Vec<Move>;
-    fn score(&self)          -> f64;
-    fn set(&self, m: Move)   -> Self;
-    fn finished(&self)       -> bool;
-    fn current_player(&self) -> Player;
+    fn valid_moves(&self)           -> Vec<Move>;
+    fn score(&self)                 -> f64;
+    fn set(&self, m: Move)          -> Self;
+    fn finished(&self)              -> bool;
+    fn current_player(&self)        -> Player;
+    fn hash(&self)                  -> u64;
+    fn capture_pending(&self)       -> bool;
+    fn is_capture(&self, m: Move)   -> bool;
+    fn is_promotion(&self, m: Move) -> bool;
+    // Makes the move m on the game itself. It is taken back by unmake.
+    fn make(&mut self, m: Move);
+    fn unmake(&mut self);
 }
 
+// Upper bound for the number of plies searched with a time budget.
+const MAX_DEPTH: usize = 64;
+
 pub struct MiniMax<Move> {
     score_cnt: usize,
+    cut_cnt: usize,
     max_recursion: usize,
+    max_depth: usize,
+    time_budget: Option<i64>,
+    alpha_beta: bool,
+    quiescence: bool,
+    ordering: bool,
+    make_unmake: bool,
+    threads: usize,
+    pool: Option<CpuPool>,
+    stop: Option<Arc<AtomicBool>>,
+    tt: TranspositionTable<Move>,
+    order: MoveOrder<Move>,
     score_winner: f64,
     start: PreciseTime,
     duration: i64,
@@ -58,12 +88,228 @@ impl<Move: Clone + Copy + Debug> Path<Move> {
 // -------------------------------------------------------------------------------------------------
 
 
+#[derive(Clone)]
+struct Deadline {
+
+    start: PreciseTime,
+    ms: Option<i64>,
+    stop: Option<Arc<AtomicBool>>
+}
+
+impl Deadline {
+
+    pub fn new(start: PreciseTime, ms: Option<i64>, stop: Option<Arc<AtomicBool>>) -> Deadline {
+        Deadline {
+            start: start,
+            ms: ms,
+            stop: stop
+        }
+    }
+
+    pub fn none() -> Deadline {
+        Deadline::new(PreciseTime::now(), None, None)
+    }
+
+    pub fn expired(&self) -> bool {
+        let stopped = match self.stop {
+            Some(ref s) => s.load(Ordering::Relaxed),
+            _ => false
+        };
+        stopped || match self.ms {
+            Some(ms) => self.start.to(PreciseTime::now()).num_milliseconds() >= ms,
+            _ => false
+        }
+    }
+}
+
+
+// -------------------------------------------------------------------------------------------------
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry<Move> {
+
+    key: u64,
+    depth: usize,
+    score: f64,
+    bound: Bound,
+    best: Option<Move>,
+    complete: bool
+}
+
+// Number of independently locked parts of the transposition table.
+const TABLE_SHARDS: usize = 64;
+
+// Fixed-size table of searched positions indexed by their Zobrist hash. A new entry always
+// replaces the entry stored in its slot. Clones share the entries, so that the threads of a
+// parallel search can use the results of each other, but count their hits and misses separately.
+#[derive(Clone)]
+struct TranspositionTable<Move> {
+
+    shards: Arc<Vec<Mutex<Vec<Option<Entry<Move>>>>>>,
+    shard_len: usize,
+    hits: usize,
+    misses: usize
+}
+
+impl<Move: Clone + Copy + Debug> TranspositionTable<Move> {
+
+    pub fn new(bytes: usize) -> TranspositionTable<Move> {
+        let n = bytes / size_of::<Option<Entry<Move>>>();
+        let k = if n < TABLE_SHARDS { n.min(1) } else { TABLE_SHARDS };
+        TranspositionTable {
+            shards: Arc::new((0..k).map(|_| Mutex::new(vec![None; n / k])).collect()),
+            shard_len: if k > 0 { n / k } else { 0 },
+            hits: 0,
+            misses: 0
+        }
+    }
+
+    // Returns the shard and the slot in that shard for a key.
+    fn slot(&self, key: u64) -> (usize, usize) {
+        let k = self.shards.len() as u64;
+        ((key % k) as usize, ((key / k) % self.shard_len as u64) as usize)
+    }
+
+    pub fn probe(&mut self, key: u64) -> Option<Entry<Move>> {
+        if self.shards.is_empty() {
+            return None;
+        }
+        let (i, j) = self.slot(key);
+        let e = self.shards[i].lock().unwrap()[j];
+        match e {
+            Some(e) if e.key == key => {
+                self.hits += 1;
+                Some(e)
+            },
+            _ => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn store(&mut self, e: Entry<Move>) {
+        if !self.shards.is_empty() {
+            let (i, j) = self.slot(e.key);
+            self.shards[i].lock().unwrap()[j] = Some(e);
+        }
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+    }
+}
+
+
+// -------------------------------------------------------------------------------------------------
+
+
+// Tables to search the moves which are most likely to cause a cutoff first. Besides the move of
+// the transposition table, these are captures, promotions, killer moves (moves which caused a
+// cutoff in a sibling node at the same ply) and moves which caused many cutoffs in the whole
+// search (history).
+#[derive(Clone)]
+struct MoveOrder<Move> {
+
+    killers: Vec<[Option<Move>; 2]>,
+    history: HashMap<Move, usize>,
+    cut_nodes: usize,
+    first_cuts: usize
+}
+
+impl<Move: Clone + Copy + Debug + Eq + Hash> MoveOrder<Move> {
+
+    pub fn new() -> MoveOrder<Move> {
+        MoveOrder {
+            killers: vec![],
+            history: HashMap::new(),
+            cut_nodes: 0,
+            first_cuts: 0
+        }
+    }
+
+    pub fn sort<T>(&self, game: &T, moves: &mut Vec<Move>, hash_move: Option<Move>, rec: usize)
+        where T: Game<Move>
+    {
+        let killers = self.killers.get(rec).cloned().unwrap_or([None, None]);
+        moves.sort_by_key(|&m| {
+            let rank =
+                if Some(m) == hash_move { 16 } else { 0 } +
+                if game.is_capture(m) { 8 } else { 0 } +
+                if game.is_promotion(m) { 4 } else { 0 } +
+                if Some(m) == killers[0] { 2 } else { 0 } +
+                if Some(m) == killers[1] { 1 } else { 0 };
+            Reverse((rank, self.history.get(&m).cloned().unwrap_or(0)))
+        });
+    }
+
+    // Records that the i-th move searched in a node caused a cutoff.
+    pub fn cutoff<T>(&mut self, game: &T, m: Move, i: usize, rec: usize, depth: usize)
+        where T: Game<Move>
+    {
+        self.cut_nodes += 1;
+        if i == 0 {
+            self.first_cuts += 1;
+        }
+        if game.is_capture(m) {
+            return;
+        }
+        while self.killers.len() <= rec {
+            self.killers.push([None, None]);
+        }
+        if self.killers[rec][0] != Some(m) {
+            self.killers[rec][1] = self.killers[rec][0];
+            self.killers[rec][0] = Some(m);
+        }
+        *self.history.entry(m).or_insert(0) += depth * depth;
+    }
+
+    pub fn reset(&mut self) {
+        self.killers.clear();
+        self.cut_nodes = 0;
+        self.first_cuts = 0;
+    }
+}
+
+
+// -------------------------------------------------------------------------------------------------
+
+
+// State shared by all nodes of one alpha-beta search.
+struct Context<'a, Move: 'a> {
+
+    maxrec: usize,
+    quiescence: bool,
+    ordering: bool,
+    make_unmake: bool,
+    deadline: Deadline,
+    tt: &'a mut TranspositionTable<Move>,
+    order: &'a mut MoveOrder<Move>
+}
+
+
+// -------------------------------------------------------------------------------------------------
+
+
 #[derive(Debug, Clone)]
 struct Score<Move: Clone + Copy + Debug> {
 
     path: Path<Move>,
     score: f64,
-    score_cnt: usize
+    score_cnt: usize,
+    cut_cnt: usize,
+    // True if all leaves of the search are finished games, i.e. a deeper search cannot change
+    // the score.
+    complete: bool
 }
 
 impl<Move: Clone + Copy + Debug> Score<Move> {
@@ -73,18 +319,36 @@ impl<Move: Clone + Copy + Debug> Score<Move> {
             path: p,
             score: score,
             score_cnt: 1,
+            cut_cnt: 0,
+            complete: true
         }
     }
 
+    pub fn set_complete(&self, complete: bool) -> Score<Move> {
+        let mut s = self.clone();
+        s.complete = complete;
+        s
+    }
+
     pub fn set_n_scores(&self, n: usize) -> Score<Move> {
         let mut s = self.clone();
         s.score_cnt = n;
         s
     }
 
+    pub fn set_n_cuts(&self, n: usize) -> Score<Move> {
+        let mut s = self.clone();
+        s.cut_cnt = n;
+        s
+    }
+
     pub fn scores_count(&self) -> usize {
         self.score_cnt
     }
+
+    pub fn cuts_count(&self) -> usize {
+        self.cut_cnt
+    }
 }
 
 
@@ -92,13 +356,25 @@ impl<Move: Clone + Copy + Debug> Score<Move> {
 
 
 impl<Move> MiniMax<Move>
-    where Move: Clone + Copy + Debug
+    where Move: Clone + Copy + Debug + Eq + Hash + Send + Sync + 'static
 {
 
     pub fn new(max_recurions: usize) -> MiniMax<Move> {
         MiniMax {
             score_cnt: 0,
+            cut_cnt: 0,
             max_recursion: max_recurions,
+            max_depth: max_recurions + 1,
+            time_budget: None,
+            alpha_beta: false,
+            quiescence: false,
+            ordering: false,
+            make_unmake: false,
+            threads: 1,
+            pool: None,
+            stop: None,
+            tt: TranspositionTable::new(0),
+            order: MoveOrder::new(),
             score_winner: 0.0,
             start: PreciseTime::now(),
             duration: 0,
@@ -106,60 +382,359 @@ impl<Move> MiniMax<Move>
         }
     }
 
-    pub fn minimax<T>(&mut self, game: T) -> Move
-        where T: Game<Move> + Clone
+    // Creates a search which deepens the search iteratively until the given time in milliseconds
+    // has been used. The move of the last completed iteration is returned. The first iteration
+    // is always completed, even if it takes longer than the given time.
+    pub fn with_time_budget(ms: i64) -> MiniMax<Move> {
+        let mut m = MiniMax::new(MAX_DEPTH - 1);
+        m.time_budget = Some(ms);
+        m
+    }
+
+    // Enables or disables alpha-beta pruning. The pruned search returns the same score as the
+    // full search but computes less scores. Moves with equal scores are not chosen at random.
+    pub fn set_alpha_beta(&mut self, enabled: bool) {
+        self.alpha_beta = enabled;
+    }
+
+    // Enables or disables the quiescence search. If enabled, the search does not stop at the
+    // maximum depth as long as a capture is pending, so that the score is not computed in the
+    // middle of a capture sequence.
+    pub fn set_quiescence(&mut self, enabled: bool) {
+        self.quiescence = enabled;
+    }
+
+    // Enables or disables the ordering of moves in the alpha-beta search. Without ordering, the
+    // moves are searched in the order returned by the game, except the move of the
+    // transposition table which is searched first.
+    pub fn set_move_ordering(&mut self, enabled: bool) {
+        self.ordering = enabled;
+    }
+
+    // Enables or disables making and taking back the moves on a single game with Game::make and
+    // Game::unmake. Otherwise each move is made on a new game created by Game::set.
+    pub fn set_make_unmake(&mut self, enabled: bool) {
+        self.make_unmake = enabled;
+    }
+
+    // Sets the number of threads used by the alpha-beta search. The moves of the first ply are
+    // distributed among the threads, which share the transposition table and the best score
+    // found so far. The score is the same as with one thread.
+    pub fn set_threads(&mut self, n: usize) {
+        self.threads = n.max(1);
+        self.pool = match self.threads {
+            1 => None,
+            _ => Some(CpuPool::new(self.threads))
+        };
+    }
+
+    // Sets a flag to interrupt the search from another thread. A search with a time budget
+    // returns the move of the last completed iteration when the flag is set. The result of an
+    // interrupted search with a fixed depth is incomplete.
+    pub fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = Some(stop);
+    }
+
+    // Sets the memory in MB used by the transposition table of the alpha-beta search. The
+    // table is disabled with a size of 0, which is the default.
+    pub fn set_table_size(&mut self, mb: usize) {
+        self.tt = TranspositionTable::new(mb * 1024 * 1024);
+    }
+
+    // Returns the best move or None if there is no valid move, e.g. because the game is finished.
+    pub fn minimax<T>(&mut self, game: T) -> Option<Move>
+        where T: Game<Move> + Clone + Send + 'static
     {
         self.start = PreciseTime::now();
-        let x = self.max_recursion;
-        let m = MiniMax::_select_by(&game, 0, Path::new(), x);  // first move is done by AI
+        self.tt.reset_stats();
+        self.order.reset();
+        if game.finished() || game.valid_moves().is_empty() {
+            self.score_winner = game.score();
+            self.score_cnt = 0;
+            self.cut_cnt = 0;
+            self.max_depth = 0;
+            self.duration = 0;
+            self.path = vec![];
+            return None;
+        }
+        let m = match self.time_budget {
+            Some(ms) => self.deepen(&game, ms),
+            _ => {
+                let x = self.max_recursion;
+                let deadline = Deadline::new(self.start, None, self.stop.clone());
+                let m = self.search(&game, x, &deadline);
+                self.duration = self.start.to(PreciseTime::now()).num_milliseconds();
+                m
+            }
+        };
         self.score_winner = m.score;
-        self.duration = self.start.to(PreciseTime::now()).num_milliseconds();
         self.score_cnt = m.scores_count();
+        self.cut_cnt = m.cuts_count();
         self.path = m.path.p;
-        self.path.first().unwrap().clone()
+        self.path.first().cloned()
+    }
+
+    fn deepen<T>(&mut self, game: &T, ms: i64) -> Score<Move>
+        where T: Game<Move> + Clone + Send + 'static
+    {
+        let deadline = Deadline::new(self.start, Some(ms), self.stop.clone());
+
+        // The first iteration is not interrupted so that there is always a move.
+        let mut m = self.search(game, 0, &Deadline::none());
+        self.max_depth = 1;
+        self.duration = self.start.to(PreciseTime::now()).num_milliseconds();
+
+        // A deeper search of a game tree which has been searched to its end is not needed.
+        for x in 1..self.max_recursion + 1 {
+            if m.complete {
+                break;
+            }
+            let s = self.search(game, x, &deadline);
+            // Results of an interrupted iteration are incomplete and therefore dropped.
+            if deadline.expired() {
+                break;
+            }
+            m = s;
+            self.max_depth = x + 1;
+            self.duration = self.start.to(PreciseTime::now()).num_milliseconds();
+        }
+        m
+    }
+
+    fn search<T>(&mut self, game: &T, maxrec: usize, deadline: &Deadline) -> Score<Move>
+        where T: Game<Move> + Clone + Send + 'static
+    {
+        if self.alpha_beta && self.pool.is_some() {
+            return self.search_parallel(game, maxrec, deadline);
+        }
+        let mut g = game.clone();
+        let mut ctx = Context {
+            maxrec: maxrec,
+            quiescence: self.quiescence,
+            ordering: self.ordering,
+            make_unmake: self.make_unmake,
+            deadline: deadline.clone(),
+            tt: &mut self.tt,
+            order: &mut self.order
+        };
+        match self.alpha_beta {  // first move is done by AI
+            true => MiniMax::_select_by_alphabeta(&mut ctx, &mut g, 0, Path::new(), f64::NEG_INFINITY, f64::INFINITY),
+            _ => MiniMax::_select_by(&mut ctx, &mut g, 0, Path::new())
+        }
+    }
+
+    fn search_parallel<T>(&mut self, game: &T, maxrec: usize, deadline: &Deadline) -> Score<Move>
+        where T: Game<Move> + Clone + Send + 'static
+    {
+        let player = game.current_player();
+        let key = game.hash();
+        let hash_move = self.tt.probe(key).and_then(|e| e.best);
+        let mut moves = game.valid_moves();
+        if moves.is_empty() {
+            return Score::new(game.score(), Path::new()).set_complete(game.finished());
+        }
+        if self.ordering {
+            self.order.sort(game, &mut moves, hash_move, 0);
+        }
+
+        let moves = Arc::new(moves);
+        let next = Arc::new(AtomicUsize::new(0));
+        let bound = Arc::new(Mutex::new(match player {
+            Player::AI    => f64::NEG_INFINITY,
+            Player::HUMAN => f64::INFINITY
+        }));
+
+        // Each thread takes the next move which has not been searched yet. The window is
+        // narrowed by the best score found by any thread.
+        let tasks = (0..self.threads).map(|_| {
+            let mut game = game.clone();
+            let moves = moves.clone();
+            let next = next.clone();
+            let bound = bound.clone();
+            let mut tt = self.tt.clone();
+            let mut order = self.order.clone();
+            let quiescence = self.quiescence;
+            let ordering = self.ordering;
+            let make_unmake = self.make_unmake;
+            let deadline = deadline.clone();
+            tt.reset_stats();
+            order.reset();
+            self.pool.as_ref().unwrap().spawn_fn(move || -> Result<_, ()> {
+                let mut r = vec![];
+                {
+                    let mut ctx = Context {
+                        maxrec: maxrec,
+                        quiescence: quiescence,
+                        ordering: ordering,
+                        make_unmake: make_unmake,
+                        deadline: deadline,
+                        tt: &mut tt,
+                        order: &mut order
+                    };
+                    loop {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        if i >= moves.len() {
+                            break;
+                        }
+                        let b = *bound.lock().unwrap();
+                        let s = match player {
+                            Player::AI    => MiniMax::_ai_alphabeta(&mut ctx, &mut game, moves[i], 0, Path::new(), b, f64::INFINITY),
+                            Player::HUMAN => MiniMax::_ai_alphabeta(&mut ctx, &mut game, moves[i], 0, Path::new(), f64::NEG_INFINITY, b)
+                        };
+                        // A score which does not improve the window is only a bound.
+                        let exact = match player {
+                            Player::AI    => s.score > b,
+                            Player::HUMAN => s.score < b
+                        };
+                        let mut b = bound.lock().unwrap();
+                        match player {
+                            Player::AI    => if s.score > *b { *b = s.score; },
+                            Player::HUMAN => if s.score < *b { *b = s.score; }
+                        }
+                        r.push((i, exact, s));
+                    }
+                }
+                Ok((r, tt, order))
+            })
+        }).collect::<Vec<_>>();
+
+        let mut scores = vec![];
+        for t in tasks {
+            let (r, tt, order) = t.wait().unwrap();
+            scores.extend(r);
+            self.tt.hits += tt.hits;
+            self.tt.misses += tt.misses;
+            self.order.cut_nodes += order.cut_nodes;
+            self.order.first_cuts += order.first_cuts;
+        }
+
+        // Keep the first move with the maximum/minimum score as the single-threaded search does.
+        scores.sort_by_key(|&(i, _, _)| i);
+        let n: usize = scores.iter().map(|&(_, _, ref s)| s.scores_count()).sum();
+        let cuts: usize = scores.iter().map(|&(_, _, ref s)| s.cuts_count()).sum();
+        let complete = scores.iter().all(|&(_, _, ref s)| s.complete);
+        let exact = scores.into_iter().filter(|&(_, e, _)| e).map(|(i, _, s)| (i, s));
+        let (i, best) = exact.fold(None, |b: Option<(usize, Score<Move>)>, (i, s)| match b {
+            Some((j, b)) => match player {
+                Player::AI    if s.score > b.score => Some((i, s)),
+                Player::HUMAN if s.score < b.score => Some((i, s)),
+                _ => Some((j, b))
+            },
+            _ => Some((i, s))
+        }).unwrap();
+
+        if !deadline.expired() {
+            self.tt.store(Entry {
+                key: key,
+                depth: maxrec + 1,
+                score: best.score,
+                bound: Bound::Exact,
+                best: Some(moves[i]),
+                complete: complete
+            });
+        }
+
+        best.set_n_scores(n).set_n_cuts(cuts).set_complete(complete)
     }
 
     pub fn path(&self) -> Vec<Move> {
         self.path.clone()
     }
 
+    // Returns the time used for the search. Like the score and the path it refers to the last
+    // completed iteration of a search with a time budget, so an interrupted iteration is not
+    // included.
     pub fn duration_ms(&self) -> i64 {
         self.duration
     }
 
+    // Returns the number of plies searched.
+    pub fn depth(&self) -> usize {
+        self.max_depth
+    }
+
     // Returns the number of scores computed.
     pub fn scores(&self) -> usize {
         self.score_cnt
     }
 
+    // Returns the number of moves which have not been searched due to alpha-beta pruning.
+    pub fn cutoffs(&self) -> usize {
+        self.cut_cnt
+    }
+
+    // Returns the number of positions found in the transposition table.
+    pub fn table_hits(&self) -> usize {
+        self.tt.hits
+    }
+
+    // Returns the number of positions not found in the transposition table.
+    pub fn table_misses(&self) -> usize {
+        self.tt.misses
+    }
+
+    // Returns the number of nodes in which the alpha-beta search had a cutoff.
+    pub fn cutoff_nodes(&self) -> usize {
+        self.order.cut_nodes
+    }
+
+    // Returns the number of nodes in which the first move searched caused a cutoff. Compared to
+    // cutoff_nodes() this shows how good the move ordering is.
+    pub fn first_move_cutoffs(&self) -> usize {
+        self.order.first_cuts
+    }
+
     // Returns the score of the chosen move.
     pub fn score(&self) -> f64 {
         self.score_winner
     }
 
-    fn _ai_minimax<T>(game: &T, m: Move, rec: usize, path: Path<Move>, maxrec: usize) -> Score<Move>
-        where T: Game<Move> + Clone
+    // Returns true if the search stops at the given game.
+    fn _leaf<T>(g: &T, rec: usize, maxrec: usize, quiescence: bool, deadline: &Deadline) -> bool
+        where T: Game<Move>
     {
+        g.finished() || (rec >= maxrec && !(quiescence && g.capture_pending())) || deadline.expired()
+    }
 
-        let g: T = game.set(m);
-        let p = path.push(m);
-
-        if g.finished() || rec >= maxrec {
-            Score::new(g.score(), p)
+    // Calls f with the game after the move m. The move is either made on the game and taken back
+    // afterwards or made on a new game.
+    fn _with_move<T, R, F>(game: &mut T, m: Move, make_unmake: bool, f: F) -> R
+        where T: Game<Move>, F: FnOnce(&mut T) -> R
+    {
+        if make_unmake {
+            game.make(m);
+            let r = f(game);
+            game.unmake();
+            r
         } else {
-            MiniMax::_select_by(&g, rec + 1, p, maxrec)
+            f(&mut game.set(m))
         }
     }
 
-    fn _select_by<T>(game: &T, rec: usize, path: Path<Move>, maxrec: usize) -> Score<Move>
+    fn _ai_minimax<T>(ctx: &mut Context<Move>, game: &mut T, m: Move, rec: usize, path: Path<Move>) -> Score<Move>
+        where T: Game<Move> + Clone
+    {
+        let p = path.push(m);
+        let make_unmake = ctx.make_unmake;
+        MiniMax::_with_move(game, m, make_unmake, |g| {
+            if MiniMax::_leaf(g, rec, ctx.maxrec, ctx.quiescence, &ctx.deadline) {
+                Score::new(g.score(), p).set_complete(g.finished())
+            } else {
+                MiniMax::_select_by(ctx, g, rec + 1, p)
+            }
+        })
+    }
+
+    fn _select_by<T>(ctx: &mut Context<Move>, game: &mut T, rec: usize, path: Path<Move>) -> Score<Move>
         where T: Game<Move> + Clone
     {
         // Compute the score for each valid move.
         let scores = game.valid_moves()
-            .iter().map(|&mv| MiniMax::_ai_minimax(game, mv, rec, path.clone(), maxrec))
+            .iter().map(|&mv| MiniMax::_ai_minimax(ctx, game, mv, rec, path.clone()))
             .collect::<Vec<_>>();
 
         let n: usize = scores.iter().map(|s| s.scores_count()).sum();
+        let complete = scores.iter().all(|s| s.complete);
 
         // Search the maximum/minimum score depending on the player.
         let x = match game.current_player() {
@@ -172,6 +747,313 @@ impl<Move> MiniMax<Move>
         // Select a move at random among the maximums/minimums.
         (**thread_rng()
             .choose(&scores.iter().filter(|s| s.score == x.score).collect::<Vec<_>>()).unwrap()
-        ).clone().set_n_scores(n)
+        ).clone().set_n_scores(n).set_complete(complete)
+    }
+
+    fn _ai_alphabeta<T>(ctx: &mut Context<Move>, game: &mut T, m: Move, rec: usize, path: Path<Move>,
+                        alpha: f64, beta: f64) -> Score<Move>
+        where T: Game<Move> + Clone
+    {
+        let p = path.push(m);
+        let make_unmake = ctx.make_unmake;
+        MiniMax::_with_move(game, m, make_unmake, |g| {
+            if MiniMax::_leaf(g, rec, ctx.maxrec, ctx.quiescence, &ctx.deadline) {
+                Score::new(g.score(), p).set_complete(g.finished())
+            } else {
+                MiniMax::_select_by_alphabeta(ctx, g, rec + 1, p, alpha, beta)
+            }
+        })
+    }
+
+    fn _select_by_alphabeta<T>(ctx: &mut Context<Move>, game: &mut T, rec: usize, path: Path<Move>,
+                               alpha: f64, beta: f64) -> Score<Move>
+        where T: Game<Move> + Clone
+    {
+        let mut moves = game.valid_moves();
+        let player = game.current_player();
+        let key = game.hash();
+        // Number of plies to search from here. It is 0 for the nodes of the quiescence search.
+        let depth = (ctx.maxrec + 1).saturating_sub(rec);
+
+        // Entries are only used at the depth they were computed for. Thus, a search with a fixed
+        // depth returns the same score no matter in which order the positions are visited. An
+        // exact score inside the window is not used, because the node would then be on the
+        // principal variation, whose path has to reach the leaf which has been scored.
+        let mut hash_move = None;
+        if let Some(e) = ctx.tt.probe(key) {
+            let hit = e.depth == depth && rec > 0 && match e.bound {
+                Bound::Exact => e.score <= alpha || e.score >= beta,
+                Bound::Lower => e.score >= beta,
+                Bound::Upper => e.score <= alpha
+            };
+            if hit {
+                return Score::new(e.score, path).set_n_scores(0).set_complete(e.complete);
+            }
+            hash_move = e.best;
+        }
+
+        if ctx.ordering {
+            ctx.order.sort(&*game, &mut moves, hash_move, rec);
+        } else if let Some(i) = hash_move.and_then(|b| moves.iter().position(|&m| m == b)) {
+            // Search the best move found previously first.
+            moves.swap(0, i);
+        }
+
+        let alpha0 = alpha;
+        let beta0 = beta;
+        let mut alpha = alpha;
+        let mut beta = beta;
+        let mut best: Option<Score<Move>> = None;
+        let mut best_move: Option<Move> = None;
+        let mut n: usize = 0;
+        let mut cuts: usize = 0;
+        let mut complete = true;
+
+        for (i, &mv) in moves.iter().enumerate() {
+            let s = MiniMax::_ai_alphabeta(ctx, game, mv, rec, path.clone(), alpha, beta);
+            n += s.scores_count();
+            cuts += s.cuts_count();
+            complete = complete && s.complete;
+
+            // Keep the first move with the maximum/minimum score depending on the player.
+            let better = match best {
+                Some(ref b) => match player {
+                    Player::AI    => s.score > b.score,
+                    Player::HUMAN => s.score < b.score
+                },
+                _ => true
+            };
+            match player {
+                Player::AI    => if s.score > alpha { alpha = s.score; },
+                Player::HUMAN => if s.score < beta { beta = s.score; }
+            }
+            if better {
+                best = Some(s);
+                best_move = Some(mv);
+            }
+
+            // The opponent will avoid this node, so the remaining moves need not be searched.
+            if alpha >= beta {
+                cuts += moves.len() - i - 1;
+                ctx.order.cutoff(&*game, mv, i, rec, depth);
+                break;
+            }
+        }
+
+        let best = best.unwrap();
+
+        // Scores of an interrupted search are incomplete and must not be stored.
+        if !ctx.deadline.expired() {
+            ctx.tt.store(Entry {
+                key: key,
+                depth: depth,
+                score: best.score,
+                bound: if best.score <= alpha0 {
+                    Bound::Upper
+                } else if best.score >= beta0 {
+                    Bound::Lower
+                } else {
+                    Bound::Exact
+                },
+                best: best_move,
+                complete: complete
+            });
+        }
+
+        best.set_n_scores(n).set_n_cuts(cuts).set_complete(complete)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::iter::repeat;
+    use time::PreciseTime;
+    use ai::minimax::MiniMax;
+    use board::board::{Board, Color};
+    use dame::Dame;
+    use eval::{WeightedEvaluator, Weights};
+
+    fn kings() -> Board {
+        let mut f: Vec<Color> = repeat(Color::Empty).take(8 * 8).collect();
+        f[2 * 8 + 2] = Color::WhiteDame;
+        f[2 * 8 + 6] = Color::WhiteDame;
+        f[6 * 8 + 2] = Color::BlackDame;
+        f[6 * 8 + 6] = Color::BlackDame;
+        f[5 * 8 + 1] = Color::BlackNormal;
+        Board::from(f)
+    }
+
+    #[test]
+    fn alpha_beta() {
+        for b in vec![Board::new(), kings()] {
+            let mut x = MiniMax::new(4);
+            x.minimax(Dame::new(b));
+            let mut y = MiniMax::new(4);
+            y.set_alpha_beta(true);
+            y.minimax(Dame::new(b));
+            assert_eq!(x.score(), y.score());
+            assert!(y.scores() < x.scores());
+            assert!(y.cutoffs() > 0);
+            assert_eq!(x.cutoffs(), 0);
+        }
+    }
+
+    #[test]
+    fn transposition_table() {
+        let mut x = MiniMax::new(5);
+        x.set_alpha_beta(true);
+        x.minimax(Dame::new(kings()));
+        let mut y = MiniMax::new(5);
+        y.set_alpha_beta(true);
+        y.set_table_size(1);
+        y.minimax(Dame::new(kings()));
+        assert_eq!(x.score(), y.score());
+        assert!(y.scores() < x.scores());
+        assert!(y.table_hits() > 0);
+        assert!(y.table_misses() > 0);
+        assert_eq!(x.table_hits(), 0);
+    }
+
+    #[test]
+    fn move_ordering() {
+        for b in vec![Board::new(), kings()] {
+            let mut x = MiniMax::new(6);
+            x.set_alpha_beta(true);
+            x.set_table_size(1);
+            x.minimax(Dame::new(b));
+            let mut y = MiniMax::new(6);
+            y.set_alpha_beta(true);
+            y.set_table_size(1);
+            y.set_move_ordering(true);
+            y.minimax(Dame::new(b));
+            assert_eq!(x.score(), y.score());
+            assert!(y.scores() < x.scores());
+            assert!(y.first_move_cutoffs() <= y.cutoff_nodes());
+            assert!(y.first_move_cutoffs() * x.cutoff_nodes() > x.first_move_cutoffs() * y.cutoff_nodes());
+        }
+    }
+
+    #[test]
+    fn make_unmake() {
+        for b in vec![Board::new(), kings()] {
+            for &(alpha_beta, threads) in [(false, 1), (true, 1), (true, 2)].iter() {
+                let depth = if alpha_beta { 5 } else { 3 };
+                let mut x = MiniMax::new(depth);
+                x.set_alpha_beta(alpha_beta);
+                x.set_quiescence(true);
+                x.set_move_ordering(true);
+                x.set_table_size(1);
+                let m = x.minimax(Dame::new(b)).unwrap();
+                let mut y = MiniMax::new(depth);
+                y.set_alpha_beta(alpha_beta);
+                y.set_quiescence(true);
+                y.set_move_ordering(true);
+                y.set_table_size(1);
+                y.set_threads(threads);
+                y.set_make_unmake(true);
+                let d = Dame::new(b);
+                let n = y.minimax(d.clone()).unwrap();
+                assert_eq!(x.score(), y.score());
+                if alpha_beta && threads == 1 {
+                    assert_eq!(m, n);
+                    assert_eq!(x.scores(), y.scores());
+                }
+                assert_eq!(d.b.to_json(), b.to_json());
+            }
+        }
+    }
+
+    #[test]
+    fn threads() {
+        for b in vec![Board::new(), kings()] {
+            let mut x = MiniMax::new(6);
+            x.set_alpha_beta(true);
+            x.set_quiescence(true);
+            x.set_move_ordering(true);
+            x.set_table_size(1);
+            x.minimax(Dame::new(b));
+            for &n in [2, 4].iter() {
+                let mut y = MiniMax::new(6);
+                y.set_alpha_beta(true);
+                y.set_quiescence(true);
+                y.set_move_ordering(true);
+                y.set_table_size(1);
+                y.set_threads(n);
+                y.minimax(Dame::new(b));
+                assert_eq!(x.score(), y.score());
+            }
+        }
+    }
+
+    #[test]
+    fn quiescence() {
+        // Black can move the man at (5, 5) next to the white man, which captures it at once.
+        let mut f: Vec<Color> = repeat(Color::Empty).take(8 * 8).collect();
+        f[5 * 8 + 5] = Color::BlackNormal;
+        f[7 * 8 + 1] = Color::BlackNormal;
+        f[3 * 8 + 3] = Color::WhiteNormal;
+        // only material, so that the score is known
+        let d = Dame::with_evaluator(Board::from(f), WeightedEvaluator::new(Weights::new()));
+        for _ in 0..10 {
+            let mut x = MiniMax::new(0);
+            x.set_quiescence(true);
+            let m = x.minimax(d.clone()).unwrap();
+            assert!(!(m.src() == (5, 5) && m.dst() == (4, 4)));
+            assert_eq!(x.score(), 1.0 / 12.0);
+
+            let mut y = MiniMax::new(0);
+            y.set_alpha_beta(true);
+            y.set_quiescence(true);
+            y.set_table_size(1);
+            y.minimax(d.clone());
+            assert_eq!(x.score(), y.score());
+        }
+    }
+
+    #[test]
+    fn time_budget() {
+        let t = PreciseTime::now();
+        let mut x = MiniMax::with_time_budget(50);
+        x.set_alpha_beta(true);
+        x.minimax(Dame::new(Board::new()));
+        assert!(t.to(PreciseTime::now()).num_milliseconds() < 1000);
+        assert!(x.depth() > 1);
+        assert_eq!(x.path().len(), x.depth());
+    }
+
+    #[test]
+    fn solved() {
+        // The game ends after at most four moves, so the search stops at this depth.
+        for &threads in [1, 2].iter() {
+            let t = PreciseTime::now();
+            let mut x = MiniMax::with_time_budget(10000);
+            x.set_alpha_beta(true);
+            x.set_table_size(1);
+            x.set_threads(threads);
+            x.minimax(Dame::new(Board::from_fen("B:W21,25:B9").unwrap()));
+            let ms = t.to(PreciseTime::now()).num_milliseconds();
+            assert!(ms < 1000);
+            assert_eq!(x.depth(), 4);
+            assert!(x.duration_ms() <= ms);
+        }
+    }
+
+    #[test]
+    fn no_moves() {
+        // Black cannot move.
+        let d = Dame::new(Board::from_fen("B:W25,30:B21").unwrap());
+        for &threads in [1, 2].iter() {
+            let mut x = MiniMax::new(4);
+            x.set_alpha_beta(true);
+            x.set_threads(threads);
+            assert_eq!(x.minimax(d.clone()), None);
+            assert!(x.path().is_empty());
+            let mut y = MiniMax::with_time_budget(10);
+            y.set_alpha_beta(true);
+            y.set_threads(threads);
+            assert_eq!(y.minimax(d.clone()), None);
+        }
+        assert_eq!(MiniMax::new(4).minimax(d.clone()), None);
     }
 }
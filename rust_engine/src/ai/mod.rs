@@ -1,15 +1,23 @@
 pub mod minimax;
+pub mod ponder;
 
 use rand;
 use rand::Rng;
 use board::board::Board;
 
-pub fn random(b: &mut Board) {
+// Makes a random step or jump. Returns false if there is no valid move, e.g. because the game
+// is finished.
+pub fn random(b: &mut Board) -> bool {
     // Choose a piece at random.
     let v = b.movable_pieces();
-    let e = rand::thread_rng().choose(&v).unwrap();
+    let e = match rand::thread_rng().choose(&v) {
+        Some(e) => *e,
+        None => return false
+    };
     // Choose a valid move for that piece at random.
-    let m = b.mv(e.0, e.1).unwrap();
-    let n = rand::thread_rng().choose(&m).unwrap();
-    b.move_it(e.0, e.1, n.x, n.y);
+    let m = b.mv(e.0, e.1).unwrap_or(vec![]);
+    match rand::thread_rng().choose(&m) {
+        Some(n) => b.try_move(e.0, e.1, n.x, n.y).is_ok(),
+        None => false
+    }
 }
\ No newline at end of file
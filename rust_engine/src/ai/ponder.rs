@@ -0,0 +1,169 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use time::PreciseTime;
+
+use ai::minimax::{Game, MiniMax, Player};
+
+// Searches on the opponent's time. After the AI has moved, the opponent's reply predicted by
+// MiniMax::path() is assumed and the resulting position is searched in a background thread.
+// If the opponent plays the predicted reply (ponder hit), the search is continued and its
+// result can be used. Otherwise the search is stopped.
+pub struct Ponder<Move> {
+    predicted: Vec<Move>,
+    key: u64,                   // hash of the game after the predicted reply
+    stop: Arc<AtomicBool>,
+    done: Arc<AtomicBool>,
+    handle: thread::JoinHandle<(Option<Move>, MiniMax<Move>)>,
+}
+
+impl<Move> Ponder<Move>
+    where Move: Clone + Copy + Debug + Eq + Hash + Send + Sync + 'static
+{
+    // Starts pondering on the game after the AI's move. The path has to start with the move
+    // the AI just played, as returned by MiniMax::path(). The moves of the opponent which
+    // follow are the predicted reply. The search x should have a time budget, so that it
+    // deepens iteratively until it is stopped. Returns None if there is no prediction.
+    pub fn start<T>(game: &T, path: &[Move], mut x: MiniMax<Move>) -> Option<Ponder<Move>>
+        where T: Game<Move> + Clone + Send + 'static
+    {
+        let mut g = game.clone();
+        let mut predicted = vec![];
+        for &m in path.iter().skip(1) {
+            match g.current_player() {
+                Player::HUMAN if !g.finished() => {
+                    g = g.set(m);
+                    predicted.push(m);
+                },
+                _ => break
+            }
+        }
+        if predicted.is_empty() || g.finished() {
+            return None;
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let done = Arc::new(AtomicBool::new(false));
+        x.set_stop(stop.clone());
+        let d = done.clone();
+        let key = g.hash();
+        let handle = thread::spawn(move || {
+            let m = x.minimax(g);
+            d.store(true, Ordering::SeqCst);
+            (m, x)
+        });
+
+        Some(Ponder {
+            predicted: predicted,
+            key: key,
+            stop: stop,
+            done: done,
+            handle: handle,
+        })
+    }
+
+    // Returns the predicted moves of the opponent.
+    pub fn predicted(&self) -> Vec<Move> {
+        self.predicted.clone()
+    }
+
+    // Returns true if game is the position after the predicted reply, e.g. if the opponent's
+    // moves are only known from the position.
+    pub fn is_hit<T>(&self, game: &T) -> bool
+        where T: Game<Move>
+    {
+        game.hash() == self.key
+    }
+
+    // Has to be called with the moves the opponent has actually played. On a ponder hit the
+    // search continues for at most ms milliseconds and then its move and the search are
+    // returned. Otherwise the search is stopped and None is returned.
+    pub fn reply(self, played: &[Move], ms: i64) -> Option<(Move, MiniMax<Move>)> {
+        if played != &self.predicted[..] {
+            self.stop();
+            return None;
+        }
+        let start = PreciseTime::now();
+        while !self.done.load(Ordering::SeqCst) && start.to(PreciseTime::now()).num_milliseconds() < ms {
+            thread::sleep(Duration::from_millis(1));
+        }
+        self.stop.store(true, Ordering::SeqCst);
+        match self.handle.join() {
+            Ok((Some(m), x)) => Some((m, x)),
+            _ => None
+        }
+    }
+
+    // Stops the search and waits for the background thread.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.handle.join();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use time::PreciseTime;
+    use ai::minimax::{Game, MiniMax};
+    use ai::ponder::Ponder;
+    use board::board::Board;
+    use dame::{Dame, DameMove};
+
+    fn search() -> MiniMax<DameMove> {
+        let mut x = MiniMax::with_time_budget(60000);
+        x.set_alpha_beta(true);
+        x.set_table_size(1);
+        x
+    }
+
+    #[test]
+    fn ponder() {
+        let d = Dame::new(Board::new());
+        let mut x = MiniMax::new(4);
+        x.set_alpha_beta(true);
+        let m = x.minimax(d.clone()).unwrap();
+        let g = d.set(m);
+
+        // Ponder hit.
+        let p = Ponder::start(&g, &x.path(), search()).unwrap();
+        let predicted = p.predicted();
+        assert_eq!(predicted[0], x.path()[1]);
+        let (r, y) = p.reply(&predicted, 100).unwrap();
+        assert!(y.depth() > 1);
+        assert!(g.set(predicted[0]).valid_moves().contains(&r));
+
+        // Ponder miss.
+        let p = Ponder::start(&g, &x.path(), search()).unwrap();
+        let other = g.valid_moves().into_iter().find(|&m| m != predicted[0]).unwrap();
+        let t = PreciseTime::now();
+        assert!(p.reply(&[other], 100).is_none());
+        assert!(t.to(PreciseTime::now()).num_milliseconds() < 1000);
+    }
+
+    #[test]
+    fn transposition_table() {
+        // The path of a search with the transposition table predicts a reply in each position.
+        let mut d = Dame::new(Board::new());
+        for _ in 0..6 {
+            let mut x = MiniMax::new(5);
+            x.set_alpha_beta(true);
+            x.set_quiescence(true);
+            x.set_move_ordering(true);
+            x.set_table_size(1);
+            let m = x.minimax(d.clone()).unwrap();
+            let g = d.set(m);
+            let p = Ponder::start(&g, &x.path(), search()).unwrap();
+            let predicted = p.predicted();
+            assert!(p.is_hit(&g.set(predicted[0])));
+            assert!(!p.is_hit(&g));
+            p.stop();
+
+            // Both sides play the predicted moves.
+            d = g.set(predicted[0]);
+        }
+    }
+}
@@ -1,17 +1,22 @@
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
 use rustc_serialize::json::encode;
+use std;
 use std::iter::repeat;
 
+use board::moves::{Move, MAX_HOPS};
 use board::point::Point;
+use board::rules;
+use board::rules::{CapturePromotion, RuleSet, AMERICAN};
 
 struct MoveFor {
-    pub v: [(i32, i32); 4],
+    pub v: [u8; 4],
     pub n: usize
 }
 
 impl MoveFor {
     pub fn new() -> MoveFor {
         MoveFor {
-            v: [(0, 0); 4],
+            v: [0; 4],
             n: 0,
         }
     }
@@ -33,16 +38,204 @@ pub enum Player {
     None
 }
 
-#[derive(RustcEncodable, RustcDecodable, Clone, Debug)]
+// Why a game ended in a draw.
+#[derive(Debug, Clone, Copy, RustcEncodable, RustcDecodable, PartialEq, Eq)]
+pub enum DrawReason {
+    Repetition,     // the same position with the same player to move occurred three times
+    MoveRule,       // no capture and no move of a man within the moves of the move rule
+}
+
+#[derive(Debug, Clone, Copy, RustcEncodable, RustcDecodable, PartialEq, Eq)]
+pub enum GameOutcome {
+    Ongoing,
+    WhiteWins,
+    BlackWins,
+    Draw(DrawReason)
+}
+
+// Why a single step or jump was rejected by Board::try_move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    GameFinished,
+    NotOnBoard,         // a square is outside of the board or a light square
+    NotYourPiece,       // there is no piece of the player to move on the source square
+    MustContinueJump,   // another piece has to continue its jump
+    CaptureMandatory,   // the player has to capture a piece
+    Unreachable,        // the piece cannot move to the destination
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match *self {
+            MoveError::GameFinished => "the game is finished",
+            MoveError::NotOnBoard => "the square is not a dark square of the board",
+            MoveError::NotYourPiece => "there is no piece of the player to move on this square",
+            MoveError::MustContinueJump => "the jump has to be continued with the same piece",
+            MoveError::CaptureMandatory => "capturing is mandatory",
+            MoveError::Unreachable => "the piece cannot move to this square",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+// What happened on a single step or jump made by Board::try_move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveOutcome {
+    pub captured: Option<(i32, i32)>,   // square of the captured piece
+    pub promoted: bool,                 // the piece has become a dame
+    pub turn_over: bool,                // the other player is to move or the game is finished
+}
+
+// Default number of moves of each player without a capture and without a move of a man after
+// which the game is a draw.
+pub const DEFAULT_MOVE_RULE: usize = 40;
+
+// Largest move rule which can be set.
+pub const MAX_MOVE_RULE: usize = 50;
+
+// The pieces are stored in three bitboards which use bit n - 1 for the square with number n of
+// the standard numbering of the 32 dark squares, i.e. square 1 is (1, 7), square 5 is (0, 6) and
+// square 32 is (6, 0). Black starts on the squares 1 - 12 and white on the squares 21 - 32.
+#[derive(Clone, Copy, Debug)]
 pub struct Board {
-    board_bitmask: u64,
-    positions: Vec<(i32, i32)>,
-    board: Vec<Color>,
+    white: u32,
+    black: u32,
+    kings: u32,
+    movable: u32,                       // pieces the player to move is allowed to move
+    next_move: Player,
+    jumping: Option<u8>,                // piece which has to continue a jump
+    outcome: GameOutcome,
+    move_rule: usize,                   // see set_move_rule
+    reversible: usize,                  // moves since the last capture or move of a man
+    last_moves: [(u8, u8); MAX_HOPS],   // moves of the current turn as (source, destination)
+    n_last_moves: usize,
+    move_no: i32,
+    hash: u64,
+    rules: &'static RuleSet,
+    capture_backwards: bool,            // copies of the rules which are needed often
+    capture_majority: bool,
+}
+
+// What a move changed on a board, see Board::make: the squares the moving piece started and
+// ended on, the captured pieces and the state of the board before the move.
+#[derive(Clone, Copy, Debug)]
+pub struct Undo {
+    from: u8,
+    to: u8,
+    captured: u32,      // squares of the captured pieces
+    kings: u32,         // captured pieces which were dames
+    promoted: bool,
     next_move: Player,
-    valid_pieces_to_move: Vec<(i32, i32)>,
-    winner: Player,
-    last_moves: Vec<(i32, i32, i32, i32)>,
+    movable: u32,
+    jumping: Option<u8>,
+    outcome: GameOutcome,
     move_no: i32,
+    hash: u64,
+    reversible: usize,
+}
+
+// Marks a missing square in STEP and JUMP.
+const NONE: u8 = 32;
+
+// The directions (dx, dy) a piece can move to are indexed by 0 = (-1, 1), 1 = (1, 1),
+// 2 = (-1, -1) and 3 = (1, -1). White men move in the directions 0 and 1, black men in 2 and 3.
+const WHITE_DIRECTIONS: &'static [usize] = &[0, 1];
+const BLACK_DIRECTIONS: &'static [usize] = &[2, 3];
+const DAME_DIRECTIONS: &'static [usize] = &[0, 1, 2, 3];
+
+// STEP[s][d] is the square next to square s in direction d, JUMP[s][d] is the square behind it.
+const STEP: [[u8; 4]; 32] = [
+    [32, 32,  4,  5], [32, 32,  5,  6], [32, 32,  6,  7], [32, 32,  7, 32],
+    [32,  0, 32,  8], [ 0,  1,  8,  9], [ 1,  2,  9, 10], [ 2,  3, 10, 11],
+    [ 4,  5, 12, 13], [ 5,  6, 13, 14], [ 6,  7, 14, 15], [ 7, 32, 15, 32],
+    [32,  8, 32, 16], [ 8,  9, 16, 17], [ 9, 10, 17, 18], [10, 11, 18, 19],
+    [12, 13, 20, 21], [13, 14, 21, 22], [14, 15, 22, 23], [15, 32, 23, 32],
+    [32, 16, 32, 24], [16, 17, 24, 25], [17, 18, 25, 26], [18, 19, 26, 27],
+    [20, 21, 28, 29], [21, 22, 29, 30], [22, 23, 30, 31], [23, 32, 31, 32],
+    [32, 24, 32, 32], [24, 25, 32, 32], [25, 26, 32, 32], [26, 27, 32, 32],
+];
+
+const JUMP: [[u8; 4]; 32] = [
+    [32, 32, 32,  9], [32, 32,  8, 10], [32, 32,  9, 11], [32, 32, 10, 32],
+    [32, 32, 32, 13], [32, 32, 12, 14], [32, 32, 13, 15], [32, 32, 14, 32],
+    [32,  1, 32, 17], [ 0,  2, 16, 18], [ 1,  3, 17, 19], [ 2, 32, 18, 32],
+    [32,  5, 32, 21], [ 4,  6, 20, 22], [ 5,  7, 21, 23], [ 6, 32, 22, 32],
+    [32,  9, 32, 25], [ 8, 10, 24, 26], [ 9, 11, 25, 27], [10, 32, 26, 32],
+    [32, 13, 32, 29], [12, 14, 28, 30], [13, 15, 29, 31], [14, 32, 30, 32],
+    [32, 17, 32, 32], [16, 18, 32, 32], [17, 19, 32, 32], [18, 32, 32, 32],
+    [32, 21, 32, 32], [20, 22, 32, 32], [21, 23, 32, 32], [22, 32, 32, 32],
+];
+
+// Offsets of the Zobrist keys. There is one key for each square and piece, one for the side to
+// move and one for each square a piece can continue a jump from.
+const ZOBRIST_WHITE_TO_MOVE: u64 = 32 * 5;
+const ZOBRIST_JUMPING: u64 = 32 * 5 + 1;
+
+// Returns the Zobrist key with the given number. The keys are generated with SplitMix64 so that
+// no table of random numbers is required.
+fn zobrist(n: u64) -> u64 {
+    let mut z = (n + 1).wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn zobrist_piece(s: usize, c: Color) -> u64 {
+    match c {
+        Color::Empty => 0,
+        _ => zobrist(s as u64 * 5 + c as u64)
+    }
+}
+
+fn bit(s: usize) -> u32 {
+    1 << s
+}
+
+// Iterates over the squares of the bits set in a bitboard in ascending order.
+struct Squares(u32);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let s = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(s)
+    }
+}
+
+fn squares(v: u32) -> Squares {
+    Squares(v)
+}
+
+// Returns the square (0 - 31) at (x, y) or None if (x, y) is not a dark square of the board.
+pub fn square(x: i32, y: i32) -> Option<usize> {
+    if x >= 0 && x < 8 && y >= 0 && y < 8 && (x + y) % 2 == 0 {
+        Some(((7 - y) * 4 + x / 2) as usize)
+    } else {
+        None
+    }
+}
+
+// Returns the piece c after it has moved to square s, i.e. a dame if c is a man which reaches the
+// row on which it is crowned.
+fn promoted(c: Color, s: usize) -> Color {
+    match c {
+        Color::BlackNormal if s >= 28 => Color::BlackDame,
+        Color::WhiteNormal if s < 4 => Color::WhiteDame,
+        _ => c
+    }
+}
+
+// Returns the coordinates (x, y) of the square s (0 - 31).
+pub fn coords(s: usize) -> (i32, i32) {
+    let y = 7 - (s / 4) as i32;
+    (2 * (s % 4) as i32 + y % 2, y)
 }
 
 impl Board {
@@ -65,88 +258,255 @@ impl Board {
         Board::from(brd)
     }
 
-    fn create_positions(v: &Vec<Color>) -> Vec<(i32, i32)> {
-        v.iter().enumerate()
-            .filter(|&(_, c)| *c != Color::Empty)
-            .map(|(idx, _)| (idx as i32 % 8, idx as i32 / 8)).collect::<Vec<_>>()
+    // Creates a board from 64 squares indexed by y * 8 + x with black to move. Pieces on light
+    // squares are ignored.
+    pub fn from(v: Vec<Color>) -> Board {
+        Board::setup(v, Player::Black)
     }
 
-    fn remove_position(&mut self, x: i32, y: i32) {
-        let p = self.positions.iter().position(|&i| i == (x, y)).unwrap();
-        self.positions.swap_remove(p);
+    // Creates a board from 64 squares indexed by y * 8 + x with player p to move.
+    pub fn setup(v: Vec<Color>, p: Player) -> Board {
+        let mut r = Board {
+            white: 0,
+            black: 0,
+            kings: 0,
+            movable: 0,
+            next_move: p,
+            jumping: None,
+            outcome: GameOutcome::Ongoing,
+            move_rule: DEFAULT_MOVE_RULE,
+            reversible: 0,
+            last_moves: [(0, 0); MAX_HOPS],
+            n_last_moves: 0,
+            move_no: 0,
+            hash: 0,
+            rules: &AMERICAN,
+            capture_backwards: AMERICAN.men_capture_backwards(),
+            capture_majority: AMERICAN.capture_majority(),
+        };
+        for (i, &c) in v.iter().enumerate().take(64) {
+            if let Some(s) = square(i as i32 % 8, i as i32 / 8) {
+                r.put(s, c);
+            }
+        }
+        r.update_valid_pieces_to_move();
+        r.rehash();
+        r
     }
 
-    fn create_bitmask(&mut self) {
-        self.board_bitmask = 0;
-        for x in 0..8 {
-            for y in 0..8 {
-                let p = self.index(x, y).unwrap();
-                if self.board[p] != Color::Empty {
-                    self.set_bit(p);
-                }
-            }
+    // Creates a board from 64 squares indexed by y * 8 + x with player p to move and derives
+    // the bitboards, the movable pieces, the hash and the outcome from them. Unlike setup, the
+    // squares are checked and the board is validated; all problems found are returned.
+    pub fn rebuild(v: &[Color], p: Player) -> Result<Board, Vec<String>> {
+        if v.len() != 64 {
+            return Err(vec!["the board must have 64 squares".to_string()]);
+        }
+        let mut e: Vec<String> = v.iter().enumerate()
+            .filter(|&(i, &c)| c != Color::Empty && square(i as i32 % 8, i as i32 / 8).is_none())
+            .map(|(i, _)| format!("a piece is on the light square ({}, {})", i % 8, i / 8))
+            .collect();
+        let mut b = Board::setup(v.to_vec(), p);
+        if b.movable == 0 && p != Player::None {
+            let o = b.check_outcome(b.other_player(p));
+            b.set_outcome(o);
         }
+        if let Err(v) = b.validate() {
+            e.extend(v);
+        }
+        if e.is_empty() { Ok(b) } else { Err(e) }
     }
 
-    fn set_bit(&mut self, p: usize) {
-        let mut i: u64 = 1;
-        i <<= p;
-        self.board_bitmask |= i;
+    // Puts the piece c on square s.
+    fn put(&mut self, s: usize, c: Color) {
+        let b = bit(s);
+        self.white &= !b;
+        self.black &= !b;
+        self.kings &= !b;
+        match c {
+            Color::WhiteNormal => self.white |= b,
+            Color::WhiteDame => { self.white |= b; self.kings |= b; },
+            Color::BlackNormal => self.black |= b,
+            Color::BlackDame => { self.black |= b; self.kings |= b; },
+            Color::Empty => {}
+        }
     }
 
-    fn clear_bit(&mut self, p: usize) {
-        let mut i: u64 = 1;
-        i <<= p;
-        i = !i;
-        self.board_bitmask &= i;
+    // Returns the piece on square s.
+    fn piece(&self, s: usize) -> Color {
+        let b = bit(s);
+        let k = self.kings & b != 0;
+        if self.white & b != 0 {
+            if k { Color::WhiteDame } else { Color::WhiteNormal }
+        } else if self.black & b != 0 {
+            if k { Color::BlackDame } else { Color::BlackNormal }
+        } else {
+            Color::Empty
+        }
     }
 
-    pub fn from(v: Vec<Color>) -> Board {
-        let mut r = Board {
-            board_bitmask: 0,
-            positions: Board::create_positions(&v),
-            board: v,
-            next_move: Player::Black,
-            valid_pieces_to_move: vec![],
-            winner: Player::None,
-            last_moves: vec![],
-            move_no: 0,
-        };
-        r.create_bitmask();
-        r.update_valid_pieces_to_move();
-        r
+    // Returns the 64 squares of the board indexed by y * 8 + x.
+    fn squares64(&self) -> Vec<Color> {
+        (0..64).map(|i| self.color(i % 8, i / 8).unwrap()).collect()
+    }
+
+    // Computes the Zobrist hash of the position from scratch. It covers the pieces, the side to
+    // move and the piece which has to continue a jump.
+    fn compute_hash(&self) -> u64 {
+        let mut h = (0..32).fold(0, |h, s| h ^ zobrist_piece(s, self.piece(s)));
+        if self.next_move == Player::White {
+            h ^= zobrist(ZOBRIST_WHITE_TO_MOVE);
+        }
+        if let Some(s) = self.jumping {
+            h ^= zobrist(ZOBRIST_JUMPING + s as u64);
+        }
+        h
+    }
+
+    // Recomputes the hash, e.g. after the board has been decoded. The move rule starts again from
+    // this position.
+    pub fn rehash(&mut self) {
+        self.hash = self.compute_hash();
+        self.reversible = 0;
+    }
+
+    // Returns the Zobrist hash of the position which is updated incrementally by move_it.
+    pub fn hash(&self) -> u64 {
+        self.hash
     }
 
+    // Returns the moves of the current turn or, if the turn is complete, of the last turn.
     pub fn get_last_moves(&self) -> Vec<(i32, i32, i32, i32)> {
-        self.last_moves.clone()
+        self.last_moves.iter().take(self.n_last_moves).map(|&(s, d)| {
+            let (x, y) = coords(s as usize);
+            let (dx, dy) = coords(d as usize);
+            (x, y, dx, dy)
+        }).collect()
     }
 
     pub fn player(&self) -> Player {
         self.next_move
     }
 
+    // Returns the number of single steps and jumps made on the board.
+    pub fn move_no(&self) -> i32 {
+        self.move_no
+    }
+
     pub fn winner(&self) -> Player {
-        self.winner
+        match self.outcome {
+            GameOutcome::WhiteWins => Player::White,
+            GameOutcome::BlackWins => Player::Black,
+            _ => Player::None
+        }
+    }
+
+    pub fn outcome(&self) -> GameOutcome {
+        self.outcome
+    }
+
+    // Returns the number of moves since the last capture or move of a man, i.e. since the last
+    // move which cannot be taken back.
+    pub fn reversible_moves(&self) -> usize {
+        self.reversible
+    }
+
+    // Ends an ongoing game in a draw by repetition. The board does not keep the earlier
+    // positions of the game; they are kept by Positions, which calls this.
+    pub fn set_repetition(&mut self) {
+        if self.outcome == GameOutcome::Ongoing {
+            self.set_outcome(GameOutcome::Draw(DrawReason::Repetition));
+        }
+    }
+
+    // Sets the number of moves of each player without a capture and without a move of a man
+    // after which the game is a draw. 0 disables the rule; at most MAX_MOVE_RULE is used.
+    pub fn set_move_rule(&mut self, n: usize) {
+        self.move_rule = std::cmp::min(n, MAX_MOVE_RULE);
+    }
+
+    pub fn move_rule(&self) -> usize {
+        self.move_rule
+    }
+
+    // Plays by the rules r from now on; the pieces which can be moved are recomputed.
+    pub fn set_rules(&mut self, r: &'static RuleSet) {
+        self.use_rules(r);
+        if self.jumping.is_none() {
+            self.update_valid_pieces_to_move();
+            let p = self.other_player(self.next_move);
+            let o = self.check_outcome(p);
+            self.set_outcome(o);
+        }
+    }
+
+    pub fn rules(&self) -> &'static RuleSet {
+        self.rules
+    }
+
+    fn use_rules(&mut self, r: &'static RuleSet) {
+        self.rules = r;
+        self.capture_backwards = r.men_capture_backwards();
+        self.capture_majority = r.capture_majority();
+    }
+
+    pub fn set_move_no(&mut self, n: i32) {
+        self.move_no = n;
+    }
+
+    // Returns the piece which has to continue a jump or None at the start of a turn.
+    pub fn jumping(&self) -> Option<(i32, i32)> {
+        self.jumping.map(|j| coords(j as usize))
+    }
+
+    // Makes the piece on (x, y) of the player to move continue a jump, e.g. in a position which
+    // has been set up. Fails if there is no such piece or if it cannot capture.
+    pub fn set_jumping(&mut self, x: i32, y: i32) -> Result<(), String> {
+        match square(x, y) {
+            Some(s) if self.can_remove_piece(s) => {
+                self.jumping = Some(s as u8);
+                self.movable = bit(s);
+                self.outcome = GameOutcome::Ongoing;
+                self.rehash();
+                Ok(())
+            },
+            _ => Err(format!("the piece on ({}, {}) cannot continue a jump", x, y))
+        }
     }
 
     pub fn movable_pieces(&self) -> Vec<(i32, i32)> {
-        self.valid_pieces_to_move.clone()
+        squares(self.movable).map(coords).collect()
+    }
+
+    // Returns true if the piece at (x, y) becomes a dame when moved to row dy.
+    pub fn is_promotion(&self, x: i32, y: i32, dy: i32) -> bool {
+        match self.color(x, y) {
+            Some(Color::WhiteNormal) => dy == 7,
+            Some(Color::BlackNormal) => dy == 0,
+            _ => false
+        }
+    }
+
+    // Returns true if the player to move has to capture a piece of the opponent.
+    pub fn must_capture(&self) -> bool {
+        squares(self.movable).any(|s| self.can_remove_piece(s))
     }
 
     pub fn valid_moves(&self) -> Vec<(i32, i32, i32, i32)> {
-        // TODO refactoring
         let mut v: Vec<(i32, i32, i32, i32)> = Vec::new();
-        for i in self.movable_pieces() {
-            let mf = self.moves_for(i.0, i.1);
-            for k in 0..mf.n {
-                v.push((i.0, i.1, mf.v[k].0, mf.v[k].1));
+        for s in squares(self.movable) {
+            let (x, y) = coords(s);
+            let mf = self.moves_for(s);
+            for &d in mf.v.iter().take(mf.n) {
+                let (dx, dy) = coords(d as usize);
+                v.push((x, y, dx, dy));
             }
         }
         v
     }
 
+    // Returns true if a player has won or if the game is a draw.
     pub fn finished(&self) -> bool {
-        self.winner != Player::None
+        self.outcome != GameOutcome::Ongoing
     }
 
     pub fn to_json(&self) -> String {
@@ -161,13 +521,19 @@ impl Board {
         }
     }
 
-    fn color(&self, x: i32, y: i32) -> Option<Color> {
-        match self.index(x, y) {
-            Some(p) => Some(self.board[p]),
+    pub fn color(&self, x: i32, y: i32) -> Option<Color> {
+        match (self.index(x, y), square(x, y)) {
+            (Some(_), Some(s)) => Some(self.piece(s)),
+            (Some(_), None) => Some(Color::Empty),
             _ => None
         }
     }
 
+    #[cfg(test)]
+    fn is_color(&self, x: i32, y: i32, c: Color) -> bool {
+        self.color(x, y) == Some(c)
+    }
+
     fn is_black(&self, c: Color) -> bool {
         (c == Color::BlackNormal || c == Color::BlackDame)
     }
@@ -181,24 +547,20 @@ impl Board {
         (self.next_move == Player::White && self.is_white(c))
     }
 
+    #[cfg(test)]
     fn is_empty(&self, x: i32, y: i32) -> bool {
-
-        match self.index(x, y) {
-            Some(p) => {
-                let mut i: u64 = 1;
-                i <<= p;
-                (self.board_bitmask & i) == 0
-            },
+        match square(x, y) {
+            Some(s) => (self.white | self.black) & bit(s) == 0,
             _ => false
         }
     }
 
-    fn is_player(&self, x: i32, y: i32, p: Player) -> bool {
-        let c = self.color(x, y).unwrap();
+    // Returns the bitboard with the pieces of player p.
+    fn pieces(&self, p: Player) -> u32 {
         match p {
-            Player::Black => self.is_black(c),
-            Player::White => self.is_white(c),
-            _ => false
+            Player::White => self.white,
+            Player::Black => self.black,
+            Player::None => 0
         }
     }
 
@@ -206,232 +568,666 @@ impl Board {
         match p {
             Player::Black => Player::White,
             Player::White => Player::Black,
-            _ => { panic!("invlaid player"); }
+            Player::None => Player::None
         }
     }
 
-    fn is_normal(&self, x: i32, y: i32) -> bool {
-        let p = self.index(x, y).unwrap();
-        self.board[p] == Color::BlackNormal || self.board[p] == Color::WhiteNormal
-    }
-
-    fn is_dame(&self, x: i32, y: i32) -> bool {
-        let p = self.index(x, y).unwrap();
-        self.board[p] == Color::BlackDame || self.board[p] == Color::WhiteDame
-    }
-
     pub fn count_normal(&self, p: Player) -> i32 {
-        self.positions.iter()
-            .filter(|&&(x, y)| self.is_player(x, y, p) && self.is_normal(x, y)).count() as i32
+        (self.pieces(p) & !self.kings).count_ones() as i32
     }
 
     pub fn count_dame(&self, p: Player) -> i32 {
-        self.positions.iter()
-            .filter(|&&(x, y)| self.is_player(x, y, p) && self.is_dame(x, y)).count() as i32
+        (self.pieces(p) & self.kings).count_ones() as i32
     }
 
     pub fn positions(&self, p: Player) -> Vec<(i32, i32)> {
-        self.positions.iter()
-            .filter(|&&(x, y)| self.is_player(x, y, p)).cloned().collect()
+        squares(self.pieces(p)).map(coords).collect()
     }
 
-    fn move_piece(&self, x: i32, y: i32, dy: i32, p: Player, v: &mut [(i32, i32)], pos: usize) -> usize {
+    // Returns the board rotated by 180 degrees with the colors of the pieces and the player to
+    // move swapped. As men of both colors move away from their own side, this is the symmetry
+    // of the rules: the flipped position has the same moves, mirrored, and the same outcome
+    // with the colors swapped. Rotating the board maps square s to 31 - s.
+    pub fn flip_colors(&self) -> Board {
+        let r = self.rotate();
+        let mut b = r;
+        b.white = r.black;
+        b.black = r.white;
+        b.next_move = self.other_player(self.next_move);
+        b.outcome = match self.outcome {
+            GameOutcome::WhiteWins => GameOutcome::BlackWins,
+            GameOutcome::BlackWins => GameOutcome::WhiteWins,
+            o => o
+        };
+        b.hash = b.compute_hash();
+        b
+    }
 
-        let mut i = pos;
-        let mut u = false;
+    // Returns the pieces, the movable pieces, the jumping piece and the last moves rotated by
+    // 180 degrees.
+    fn rotate(&self) -> Board {
+        let rotate = |v: u32| squares(v).fold(0, |r, s| r | bit(31 - s));
+        let mut b = *self;
+        b.white = rotate(self.white);
+        b.black = rotate(self.black);
+        b.kings = rotate(self.kings);
+        b.movable = rotate(self.movable);
+        b.jumping = self.jumping.map(|j| 31 - j);
+        for m in b.last_moves.iter_mut().take(self.n_last_moves) {
+            *m = (31 - m.0, 31 - m.1);
+        }
+        b
+    }
 
-        if pos > 0 && (v[0].0 - x).abs() == 2 {
-            u = true;
+    // Returns the canonical form of the position, which is the position itself if black is to
+    // move and the flipped position otherwise, and whether it has been flipped. A position and
+    // its flipped position have the same canonical form, so that e.g. a cache or an opening
+    // book only has to store positions with black to move. Scores and moves of the canonical
+    // form have to be flipped back if the position has been flipped. There is no mirror
+    // symmetry: a left-right mirror maps the dark squares onto light ones.
+    pub fn canonical(&self) -> (Board, bool) {
+        if self.next_move == Player::White {
+            (self.flip_colors(), true)
+        } else {
+            (*self, false)
         }
+    }
 
-        if self.is_empty(x - 2, y + dy * 2) && self.is_player(x - 1, y + dy, p) {
-            if !u {
-                i = 0;
-            }
-            v[i] = (x - 2, y + dy * 2);
-            i += 1;
-            u = true;
+    // Returns the directions in which the piece on square s can move.
+    fn directions(&self, s: usize) -> &'static [usize] {
+        if self.kings & bit(s) != 0 {
+            DAME_DIRECTIONS
+        } else if self.white & bit(s) != 0 {
+            WHITE_DIRECTIONS
+        } else {
+            BLACK_DIRECTIONS
         }
-        if self.is_empty(x + 2, y + dy * 2) && self.is_player(x + 1, y + dy, p) {
-            if !u {
-                i = 0;
-            }
-            v[i] = (x + 2, y + dy * 2);
-            i += 1;
-            u = true;
+    }
+
+    // Returns the directions in which the piece on square s can capture.
+    fn capture_directions(&self, s: usize) -> &'static [usize] {
+        if self.capture_backwards { DAME_DIRECTIONS } else { self.directions(s) }
+    }
+
+    // Returns the directions in which the piece c can capture.
+    fn piece_capture_directions(&self, c: Color) -> &'static [usize] {
+        match c {
+            _ if self.capture_backwards => DAME_DIRECTIONS,
+            Color::WhiteDame | Color::BlackDame => DAME_DIRECTIONS,
+            Color::WhiteNormal => WHITE_DIRECTIONS,
+            _ => BLACK_DIRECTIONS
         }
+    }
+
+    // Checks if the piece on square s can jump in direction d over a piece of the opponent.
+    // opponent and occupied are the squares occupied by the opponent and by all pieces.
+    fn is_jump(s: usize, d: usize, opponent: u32, occupied: u32) -> bool {
+        let (m, j) = (STEP[s][d], JUMP[s][d]);
+        j != NONE && opponent & bit(m as usize) != 0 && occupied & bit(j as usize) == 0
+    }
 
-        // If we have found a jump over a piece of the opponent we don't have to search for other
-        // moves as the jump is mandatory.
-        if !u {
-            if self.is_empty(x - 1, y + dy) {
-                v[i] = (x - 1, y + dy);
-                i += 1;
+    // Collects the squares the piece on square s can move to if it is a piece of the current
+    // player. If the piece can jump over a piece of the opponent only the jumps are collected
+    // as the jump is mandatory.
+    fn get_moves_for(&self, s: usize, r: &mut MoveFor) {
+        r.n = 0;
+        if !self.matching(self.piece(s)) {
+            return;
+        }
+        let opponent = self.pieces(self.other_player(self.next_move));
+        let occupied = self.white | self.black;
+        let mut jump = false;
+        for &d in self.capture_directions(s) {
+            if Board::is_jump(s, d, opponent, occupied) {
+                jump = true;
+                if self.is_majority(s, d) {
+                    r.v[r.n] = JUMP[s][d];
+                    r.n += 1;
+                }
             }
-            if self.is_empty(x + 1, y + dy) {
-                v[i] = (x + 1, y + dy);
-                i += 1;
+        }
+        if !jump {
+            for &d in self.directions(s) {
+                let m = STEP[s][d];
+                if m != NONE && occupied & bit(m as usize) == 0 {
+                    r.v[r.n] = m;
+                    r.n += 1;
+                }
             }
         }
-
-        i
     }
 
-    // Checks if the piece at (x, y) of the current player can jump over a piece of the opponent.
-    fn can_remove_piece(&self, x: i32, y: i32) -> bool {
+    // Returns the squares the piece on square s can move to.
+    fn moves_for(&self, s: usize) -> MoveFor {
         let mut mf = MoveFor::new();
-        self.get_moves_for(x, y, &mut mf);
-        mf.v.iter().take(mf.n).any(|ref p| (p.0 - x).abs() == 2)
+        self.get_moves_for(s, &mut mf);
+        mf
     }
 
-    // Checks if moving the piece at (x, y) is allowed.
-    fn moving_piece_is_allowed(&self, x: i32, y: i32) -> bool {
-        self.valid_pieces_to_move.iter()
-            .any(|&(px, py)| px == x && py == y)
+    // Checks if the piece on square s of the current player can jump over a piece of the
+    // opponent.
+    fn can_jump(&self, s: usize) -> bool {
+        let opponent = self.pieces(self.other_player(self.next_move));
+        let occupied = self.white | self.black;
+        self.pieces(self.next_move) & bit(s) != 0 &&
+            self.capture_directions(s).iter().any(|&d| Board::is_jump(s, d, opponent, occupied))
     }
 
-    fn update_valid_pieces_to_move(&mut self) { // XXX
+    // Checks if the piece on square s of the current player can jump over a piece of the
+    // opponent and is allowed to by the rule of the majority.
+    fn can_remove_piece(&self, s: usize) -> bool {
+        let opponent = self.pieces(self.other_player(self.next_move));
+        let occupied = self.white | self.black;
+        self.pieces(self.next_move) & bit(s) != 0 && self.capture_directions(s).iter().any(|&d| {
+            Board::is_jump(s, d, opponent, occupied) && self.is_majority(s, d)
+        })
+    }
 
-        self.valid_pieces_to_move.clear();
+    // Checks if the jump of the piece on square s in direction d starts a capture of the most
+    // pieces if the rules require it.
+    fn is_majority(&self, s: usize, d: usize) -> bool {
+        if !self.capture_majority {
+            return true;
+        }
+        let opponent = self.pieces(self.other_player(self.next_move));
+        let occupied = self.white | self.black;
+        let most = match self.jumping {
+            Some(j) => self.longest_capture(j as usize, self.piece(j as usize), opponent, occupied),
+            None => squares(self.pieces(self.next_move))
+                .map(|t| self.longest_capture(t, self.piece(t), opponent, occupied))
+                .max().unwrap_or(0)
+        };
+        self.capture_length(s, d, self.piece(s), opponent, occupied) == most
+    }
 
-        for i in self.positions.iter() {
-            if self.can_remove_piece(i.0, i.1) {
-                self.valid_pieces_to_move.push((i.0, i.1));
-            }
+    // Returns the number of pieces the piece c on square s captures at most in a move which
+    // starts with a jump in direction d, or 0 if it cannot jump in this direction. opponent and
+    // occupied are the squares occupied by the opponent and by all pieces.
+    fn capture_length(&self, s: usize, d: usize, c: Color, opponent: u32, occupied: u32) -> usize {
+        if !Board::is_jump(s, d, opponent, occupied) {
+            return 0;
         }
+        let (m, j) = (STEP[s][d] as usize, JUMP[s][d] as usize);
+        let opponent = opponent & !bit(m);
+        let occupied = (occupied & !bit(s) & !bit(m)) | bit(j);
+        let k = promoted(c, j);
+        1 + match self.rules.capture_promotion() {
+            CapturePromotion::EndsCapture if k != c => 0,
+            CapturePromotion::ContinuesAsKing => self.longest_capture(j, k, opponent, occupied),
+            _ => self.longest_capture(j, c, opponent, occupied)
+        }
+    }
 
-        if self.valid_pieces_to_move.len() == 0 {
-            for i in self.positions.iter() {
-                if self.moves_for(i.0, i.1).n > 0 {
-                    self.valid_pieces_to_move.push((i.0, i.1));
+    // Returns the number of pieces the piece c on square s can capture at most in one move.
+    fn longest_capture(&self, s: usize, c: Color, opponent: u32, occupied: u32) -> usize {
+        self.piece_capture_directions(c).iter()
+            .map(|&d| self.capture_length(s, d, c, opponent, occupied))
+            .max().unwrap_or(0)
+    }
+
+    // Checks that the bitboards, the player to move, the piece which continues a jump, the
+    // movable pieces, the hash and the outcome fit together. All inconsistencies are returned.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut e = vec![];
+        if self.next_move == Player::None {
+            e.push("there is no player to move".to_string());
+        }
+        for s in squares(self.white & self.black) {
+            e.push(format!("square {} is occupied by both colors", s + 1));
+        }
+        for s in squares(self.kings & !(self.white | self.black)) {
+            e.push(format!("the dame on square {} has no color", s + 1));
+        }
+        for &(p, row) in &[(Player::White, 7), (Player::Black, 0)] {
+            if self.pieces(p).count_ones() > 12 {
+                e.push(format!("{:?} has more than 12 pieces", p));
+            }
+            // Only a man which continues a capture may have to wait for its crown.
+            let waiting = match self.jumping {
+                Some(j) if self.rules.capture_promotion() == CapturePromotion::AtEndOfMove => bit(j as usize),
+                _ => 0
+            };
+            for s in squares(self.pieces(p) & !self.kings & !waiting).filter(|&s| coords(s).1 == row) {
+                e.push(format!("{:?} has a man on square {} on the row on which it is crowned", p, s + 1));
+            }
+        }
+        match self.jumping {
+            Some(j) => if !self.can_remove_piece(j as usize) || self.movable != bit(j as usize) {
+                e.push("the piece which continues the jump cannot capture".to_string());
+            },
+            None => {
+                let mut b = *self;
+                b.update_valid_pieces_to_move();
+                if self.finished() {
+                    b.movable = 0;
+                }
+                if b.movable != self.movable {
+                    e.push("the movable pieces do not match the position".to_string());
                 }
             }
         }
+        if self.hash != self.compute_hash() {
+            e.push("the hash does not match the position".to_string());
+        }
+        let loser = match self.outcome {
+            GameOutcome::WhiteWins => Some(Player::Black),
+            GameOutcome::BlackWins => Some(Player::White),
+            GameOutcome::Ongoing if self.movable == 0 && self.next_move != Player::None => {
+                e.push("the player to move cannot move but the game is not finished".to_string());
+                None
+            },
+            _ => None
+        };
+        if let Some(p) = loser {
+            if self.next_move != p || self.movable != 0 {
+                e.push(format!("{:?} has not lost", p));
+            }
+        }
+        if e.is_empty() { Ok(()) } else { Err(e) }
+    }
+
+    fn update_valid_pieces_to_move(&mut self) {
+        let own = self.pieces(self.next_move);
+        self.movable = squares(own)
+            .filter(|&s| self.can_remove_piece(s))
+            .fold(0, |v, s| v | bit(s));
+        if self.movable == 0 {
+            self.movable = squares(own)
+                .filter(|&s| self.moves_for(s).n > 0)
+                .fold(0, |v, s| v | bit(s));
+        }
     }
 
     pub fn mv(&self, x: i32, y: i32) -> Option<Vec<Point>> {
         // Check if piece is allowed to be moved.
-        if self.moving_piece_is_allowed(x, y) {
-            let mf = self.moves_for(x, y);
-            Some(mf.v.iter().take(mf.n).map(|&(x, y)| Point::new(x, y)).collect())
-        } else {
-            None
+        match square(x, y) {
+            Some(s) if self.movable & bit(s) != 0 => {
+                let mf = self.moves_for(s);
+                Some(mf.v.iter().take(mf.n).map(|&d| { let (x, y) = coords(d as usize); Point::new(x, y) }).collect())
+            },
+            _ => None
         }
     }
 
-    fn get_moves_for(&self, x: i32, y: i32, r: &mut MoveFor) {
-
-        r.n = match self.color(x, y) {
-            Some(c) => {
-                if self.matching(c) {
-                    match c {
-                        Color::WhiteNormal => {
-                            self.move_piece(x, y,  1, Player::Black, &mut r.v, 0)
-                        },
-                        Color::WhiteDame => {
-                            let p = self.move_piece(x, y,  1, Player::Black, &mut r.v, 0);
-                            self.move_piece(x, y, -1, Player::Black, &mut r.v, p)
-                        },
-                        Color::BlackNormal => {
-                            self.move_piece(x, y, -1, Player::White, &mut r.v, 0)
-                        },
-                        Color::BlackDame => {
-                            let p = self.move_piece(x, y,  1, Player::White, &mut r.v, 0);
-                            self.move_piece(x, y, -1, Player::White, &mut r.v, p)
-                        },
-                        _ => 0
-                    }
-                } else {
-                    0
-                }
-            },
-            _ => 0
+    // Returns all complete moves of the player to move. A multi-jump is a single move. Different
+    // sequences of jumps are different moves even if they end on the same square. If a jump has
+    // to be continued only the moves of the jumping piece are returned.
+    pub fn moves(&self) -> Vec<Move> {
+        let mut v = vec![];
+        if !self.must_capture() {
+            // steps cannot be continued
+            return self.valid_moves().iter().map(|&(x, y, dx, dy)| Move::new(&[(x, y), (dx, dy)]).unwrap()).collect();
+        }
+        for (x, y, dx, dy) in self.valid_moves() {
+            let mut b = *self;
+            b.move_it(x, y, dx, dy);
+            Board::complete(&b, Move::new(&[(x, y), (dx, dy)]).unwrap(), &mut v);
         }
+        v
     }
 
-    // Returns points to which the piece at (x, y) can move to.
-    fn moves_for(&self, x: i32, y: i32) -> MoveFor {
-        let mut mf = MoveFor::new();
-        self.get_moves_for(x, y, &mut mf);
-        mf
+    // Adds the move m and all continuations of m if the jump of m has to be continued on b.
+    fn complete(b: &Board, m: Move, v: &mut Vec<Move>) {
+        if b.jumping.is_none() {
+            v.push(m);
+            return;
+        }
+        for (_, _, dx, dy) in b.valid_moves() {
+            let mut c = *b;
+            let (x, y) = m.dst();
+            c.move_it(x, y, dx, dy);
+            Board::complete(&c, m.extend(dx, dy).unwrap(), v);
+        }
+    }
+
+    // Makes a complete move at once. Returns false and leaves the board unchanged if the move is
+    // not valid, e.g. if a multi-jump is not complete.
+    pub fn make_move(&mut self, m: &Move) -> bool {
+        let mut b = *self;
+        for h in m.squares().windows(2) {
+            if b.try_hop(h[0] as usize, h[1] as usize).is_err() {
+                return false;
+            }
+        }
+        if b.jumping.is_some() {
+            return false;
+        }
+        *self = b;
+        true
+    }
+
+    // Makes a complete move and returns what is needed to take it back with unmake. Unlike
+    // make_move the board is not copied. Returns None if the move is not valid; the board is then
+    // unchanged except for the last moves, see unmake.
+    pub fn make(&mut self, m: &Move) -> Option<Undo> {
+        let s = m.squares()[0];
+        let mut u = Undo {
+            from: s,
+            to: s,
+            captured: 0,
+            kings: 0,
+            promoted: false,
+            next_move: self.next_move,
+            movable: self.movable,
+            jumping: self.jumping,
+            outcome: self.outcome,
+            move_no: self.move_no,
+            hash: self.hash,
+            reversible: self.reversible,
+        };
+        let (pieces, kings) = (self.white | self.black, self.kings);
+        let mut valid = true;
+        for h in m.squares().windows(2) {
+            if self.try_hop(h[0] as usize, h[1] as usize).is_err() {
+                valid = false;
+                break;
+            }
+            u.to = h[1];
+        }
+        u.captured = pieces & !(self.white | self.black) & !bit(u.from as usize);
+        u.kings = u.captured & kings;
+        u.promoted = kings & bit(u.from as usize) == 0 && self.kings & bit(u.to as usize) != 0;
+        if !valid || self.jumping.is_some() {
+            self.unmake(u);
+            return None;
+        }
+        Some(u)
+    }
+
+    // Takes back the move which returned u: the moving piece, a promotion and the captured pieces
+    // are put back and the player to move, the pieces to move, the outcome, the move number, the
+    // hash and the move rule are restored. The last moves are cleared, see History for a board on
+    // which they are restored.
+    pub fn unmake(&mut self, u: Undo) {
+        let (from, to) = (u.from as usize, u.to as usize);
+        let c = self.piece(to);
+        let (man, other, other_dame) = match c {
+            Color::WhiteNormal | Color::WhiteDame => (Color::WhiteNormal, Color::BlackNormal, Color::BlackDame),
+            _ => (Color::BlackNormal, Color::WhiteNormal, Color::WhiteDame)
+        };
+        self.put(to, Color::Empty);
+        self.put(from, if u.promoted { man } else { c });
+        for s in squares(u.captured) {
+            self.put(s, if u.kings & bit(s) != 0 { other_dame } else { other });
+        }
+        self.next_move = u.next_move;
+        self.movable = u.movable;
+        self.jumping = u.jumping;
+        self.outcome = u.outcome;
+        self.move_no = u.move_no;
+        self.hash = u.hash;
+        self.reversible = u.reversible;
+        self.n_last_moves = 0;
+    }
+
+    // Sets the hops of m as the last moves, e.g. when the move after m has been taken back.
+    pub fn set_last_moves(&mut self, m: &Move) {
+        self.n_last_moves = 0;
+        for (x, y, dx, dy) in m.hops().into_iter().take(MAX_HOPS) {
+            if let (Some(s), Some(d)) = (square(x, y), square(dx, dy)) {
+                self.last_moves[self.n_last_moves] = (s as u8, d as u8);
+                self.n_last_moves += 1;
+            }
+        }
     }
 
     pub fn clear_last_moves(&mut self) {
-        self.last_moves.clear()
+        self.n_last_moves = 0;
     }
+
+    // Moves the piece at (x, y) to (dx, dy) if this is a valid step or jump. Invalid moves are
+    // ignored; see try_move for the reason.
     pub fn move_it(&mut self, x: i32, y: i32, dx: i32, dy: i32) {
+        let _ = self.try_move(x, y, dx, dy);
+    }
 
-        // Return if piece is not allowed to be moved or if game is finished.
-        if !self.moving_piece_is_allowed(x, y) || self.winner != Player::None {
-            return;
-        }
+    // Moves the piece at (x, y) to (dx, dy) like move_it. Returns what happened or why the move
+    // was rejected, in which case the board is unchanged.
+    pub fn try_move(&mut self, x: i32, y: i32, dx: i32, dy: i32) -> Result<MoveOutcome, MoveError> {
+        let (s, d) = match (square(x, y), square(dx, dy)) {
+            (Some(s), Some(d)) => (s, d),
+            _ => return Err(MoveError::NotOnBoard)
+        };
+        self.try_hop(s, d)
+    }
 
-        // Get all valid moves for this piece.
-        let mf = self.moves_for(x, y);
+    // Makes the step or jump from square s to square d like try_move.
+    fn try_hop(&mut self, s: usize, d: usize) -> Result<MoveOutcome, MoveError> {
+        if self.finished() {
+            return Err(MoveError::GameFinished);
+        }
+        if self.pieces(self.next_move) & bit(s) == 0 {
+            return Err(MoveError::NotYourPiece);
+        }
+        if let Some(j) = self.jumping {
+            if j as usize != s {
+                return Err(MoveError::MustContinueJump);
+            }
+        }
 
-        // If (dx, dy) is not in the valid moves return.
-        if !mf.v.iter().take(mf.n).any(|ref p| p.0 == dx && p.1 == dy) {
-            return;
+        // The destination has to be one of the squares the piece can move to.
+        let mf = self.moves_for(s);
+        if self.movable & bit(s) == 0 || !mf.v.iter().take(mf.n).any(|&t| t as usize == d) {
+            let jump = (coords(d).0 - coords(s).0).abs() == 2;
+            return Err(if !jump && self.jumping.is_some() {
+                MoveError::MustContinueJump
+            } else if !jump && self.must_capture() {
+                MoveError::CaptureMandatory
+            } else {
+                MoveError::Unreachable
+            });
         }
+        Ok(self.apply(s, d))
+    }
 
-        let p = self.index(x, y).unwrap();        // position of source
-        let q = self.index(dx, dy).unwrap();      // position of destination
+    // Makes the valid step or jump from square s to square d.
+    fn apply(&mut self, s: usize, d: usize) -> MoveOutcome {
+        let (x, y) = coords(s);
+        let (dx, dy) = coords(d);
 
-        self.last_moves.push((x, y, dx, dy));
+        // A new turn starts unless a jump is continued.
+        if self.jumping.is_none() {
+            self.n_last_moves = 0;
+        }
+        if self.n_last_moves < MAX_HOPS {
+            self.last_moves[self.n_last_moves] = (s as u8, d as u8);
+            self.n_last_moves += 1;
+        }
         self.move_no += 1;
 
-        // Jump to new position.
-        self.positions.push((q as i32 % 8, q as i32 / 8));
-        self.remove_position(p as i32 % 8, p as i32 / 8);
-        self.board[q] = self.board[p];
-        self.board[p] = Color::Empty;
+        if let Some(j) = self.jumping {
+            self.hash ^= zobrist(ZOBRIST_JUMPING + j as u64);
+            self.jumping = None;
+        }
 
-        self.set_bit(q);
-        self.clear_bit(p);
+        // Jump to new position.
+        let c = self.piece(s);
+        self.hash ^= zobrist_piece(s, c) ^ zobrist_piece(d, c);
+        self.put(s, Color::Empty);
+        self.put(d, c);
 
         // If we jumped over an opponent's piece remove that.
-        let mut removed = false;
+        let mut captured = None;
         if (dx - x).abs() == 2 {
-            let pp = self.index(x + (dx - x) / 2, y + (dy - y) / 2).unwrap();
-            self.remove_position(pp as i32 % 8, pp as i32 / 8);
-            self.board[pp] = Color::Empty;
-            self.clear_bit(pp);
-            removed = true;
+            let (mx, my) = (x + (dx - x) / 2, y + (dy - y) / 2);
+            let m = square(mx, my).unwrap();
+            self.hash ^= zobrist_piece(m, self.piece(m));
+            self.put(m, Color::Empty);
+            captured = Some((mx, my));
+        }
+        let removed = captured.is_some();
+
+        let player = self.next_move;
+
+        // Check if piece needs to be converted to dame. During a capture the rules decide
+        // whether this ends the capture, whether the dame continues or whether the man
+        // continues and is only crowned at the end of the move.
+        let k = promoted(c, d);
+        let when = self.rules.capture_promotion();
+        let crown = |b: &mut Board| {
+            b.put(d, k);
+            b.hash ^= zobrist_piece(d, c) ^ zobrist_piece(d, k);
+        };
+        if k != c && when != CapturePromotion::AtEndOfMove {
+            crown(self);
         }
-
-        let player = self.next_move.clone();
 
         // If this piece removed an opponent's piece and can this piece remove another piece?
-        if removed && self.can_remove_piece(dx, dy) {
+        let ends = k != c && when == CapturePromotion::EndsCapture;
+        let turn_over = !(removed && !ends && self.can_jump(d));
+        if !turn_over {
             // Update status.
-            self.valid_pieces_to_move = vec![(dx, dy)];
+            self.movable = bit(d);
+            self.jumping = Some(d as u8);
+            self.hash ^= zobrist(ZOBRIST_JUMPING + d as u64);
             // Do not update next player.
-        } else {
+        }
+        let promoted = k != c && (turn_over || when != CapturePromotion::AtEndOfMove);
+        if promoted && when == CapturePromotion::AtEndOfMove {
+            crown(self);
+        }
+
+        if turn_over {
             // Otherwise, update next player.
             self.next_move = self.other_player(self.next_move);
+            self.hash ^= zobrist(ZOBRIST_WHITE_TO_MOVE);
             // Update next valid pieces to move for next player.
             self.update_valid_pieces_to_move();
-            // Check end.
-            if self.valid_pieces_to_move.len() == 0 {
-                self.winner = player;
+            // Captures and moves of men cannot be taken back, so no earlier position can occur
+            // again.
+            if removed || c == Color::WhiteNormal || c == Color::BlackNormal {
+                self.reversible = 0;
+            } else {
+                self.reversible += 1;
             }
+            let o = self.check_outcome(player);
+            self.set_outcome(o);
         }
 
-        // Check if piece needs to be converted to dame.
-        if dy == 0 && player == Player::Black {
-            self.board[q] = Color::BlackDame;
+        MoveOutcome {
+            captured: captured,
+            promoted: promoted,
+            turn_over: turn_over,
         }
-        if dy == 7 && player == Player::White {
-            self.board[q] = Color::WhiteDame;
+    }
+
+    // Sets the outcome. No piece can be moved in a finished game, not even after a draw.
+    fn set_outcome(&mut self, o: GameOutcome) {
+        self.outcome = o;
+        if o != GameOutcome::Ongoing {
+            self.movable = 0;
+        }
+    }
+
+    // Returns the outcome after player has completed a move.
+    fn check_outcome(&self, player: Player) -> GameOutcome {
+        if self.movable == 0 {
+            match player {
+                Player::White => GameOutcome::WhiteWins,
+                _ => GameOutcome::BlackWins
+            }
+        } else if self.move_rule > 0 && self.reversible >= 2 * self.move_rule {
+            GameOutcome::Draw(DrawReason::MoveRule)
+        } else {
+            GameOutcome::Ongoing
         }
     }
 }
 
+// The JSON representation is read by the web interface and the REST server. It contains the 64
+// squares of the board, indexed by y * 8 + x, instead of the bitboards. The positions for the
+// detection of repetitions are added by Positions::to_json.
+impl Encodable for Board {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("Board", 12, |s| {
+            try!(s.emit_struct_field("board", 0, |s| self.squares64().encode(s)));
+            try!(s.emit_struct_field("next_move", 1, |s| self.next_move.encode(s)));
+            try!(s.emit_struct_field("valid_pieces_to_move", 2, |s| self.movable_pieces().encode(s)));
+            try!(s.emit_struct_field("jumping", 3, |s| self.jumping.map(|j| coords(j as usize)).encode(s)));
+            try!(s.emit_struct_field("winner", 4, |s| self.winner().encode(s)));
+            try!(s.emit_struct_field("last_moves", 5, |s| self.get_last_moves().encode(s)));
+            try!(s.emit_struct_field("move_no", 6, |s| self.move_no.encode(s)));
+            try!(s.emit_struct_field("finished", 7, |s| self.finished().encode(s)));
+            try!(s.emit_struct_field("outcome", 8, |s| self.outcome.encode(s)));
+            try!(s.emit_struct_field("move_rule", 9, |s| self.move_rule.encode(s)));
+            try!(s.emit_struct_field("reversible_moves", 10, |s| self.reversible.encode(s)));
+            s.emit_struct_field("rules", 11, |s| self.rules.name().encode(s))
+        })
+    }
+}
+
+impl Decodable for Board {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Board, D::Error> {
+        d.read_struct("Board", 12, |d| {
+            let v: Vec<Color> = try!(d.read_struct_field("board", 0, Decodable::decode));
+            let next_move: Player = try!(d.read_struct_field("next_move", 1, Decodable::decode));
+            let jumping: Option<(i32, i32)> = try!(d.read_struct_field("jumping", 3, Decodable::decode));
+            let winner: Player = try!(d.read_struct_field("winner", 4, Decodable::decode));
+            let last_moves: Option<Vec<(i32, i32, i32, i32)>> = try!(d.read_struct_field("last_moves", 5, Decodable::decode));
+            let move_no: Option<i32> = try!(d.read_struct_field("move_no", 6, Decodable::decode));
+            let outcome: Option<GameOutcome> = try!(d.read_struct_field("outcome", 8, Decodable::decode));
+            let move_rule: Option<usize> = try!(d.read_struct_field("move_rule", 9, Decodable::decode));
+            let plies: Option<usize> = try!(d.read_struct_field("reversible_moves", 10, Decodable::decode));
+            let name: Option<String> = try!(d.read_struct_field("rules", 11, Decodable::decode));
+            let jumping = match jumping.map(|(x, y)| square(x, y)) {
+                Some(None) => return Err(d.error("the jumping piece is not on a dark square")),
+                Some(s) => s,
+                None => None
+            };
+
+            let mut b = try!(Board::rebuild(&v, next_move).map_err(|e| d.error(&e.join("; "))));
+            // Boards written before the outcome was added only have the winner.
+            b.outcome = outcome.unwrap_or(match winner {
+                Player::White => GameOutcome::WhiteWins,
+                Player::Black => GameOutcome::BlackWins,
+                Player::None => GameOutcome::Ongoing
+            });
+            if b.winner() != winner {
+                return Err(d.error("the winner does not match the outcome"));
+            }
+            b.move_rule = move_rule.map_or(DEFAULT_MOVE_RULE, |n| std::cmp::min(n, MAX_MOVE_RULE));
+            b.move_no = move_no.unwrap_or(0);
+            for &(x, y, dx, dy) in last_moves.unwrap_or(vec![]).iter().take(MAX_HOPS) {
+                if let (Some(s), Some(t)) = (square(x, y), square(dx, dy)) {
+                    b.last_moves[b.n_last_moves] = (s as u8, t as u8);
+                    b.n_last_moves += 1;
+                }
+            }
+            if let Some(n) = name {
+                match rules::by_name(&n) {
+                    Some(r) => b.use_rules(r),
+                    None => return Err(d.error(&format!("unknown rules: {}", n)))
+                }
+            }
+            match jumping {
+                Some(s) => {
+                    b.jumping = Some(s as u8);
+                    b.movable = bit(s);
+                },
+                _ => b.update_valid_pieces_to_move()
+            }
+            if b.finished() {
+                b.movable = 0;
+            }
+            b.rehash();
+            b.reversible = plies.unwrap_or(0);
+            try!(b.validate().map_err(|e| d.error(&e.join("; "))));
+            Ok(b)
+        })
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
     extern crate std;
-    use board::board::{Board, Color, Player};
+    use ai;
+    use rustc_serialize::json;
+    use rustc_serialize::json::Json;
+    use board::board::{Board, Color, DrawReason, GameOutcome, MoveError, MoveOutcome, Player, JUMP, NONE, STEP,
+                       coords, square};
+    use board::moves::Move;
+    use rand::{Rng, thread_rng};
 
     #[test]
     fn index() {
@@ -477,100 +1273,492 @@ mod tests {
         assert!(g.is_color(0, 6, Color::BlackNormal));
     }
 
-//    #[test]
-//    fn moves_for() {
-//        let g = Board::new();
-//        assert!(g.moves_for(1, 5).n == 2);
-//    }
+    #[test]
+    fn moves_for() {
+        let g = Board::new();
+        let v: Vec<(i32, i32)> = g.mv(1, 5).unwrap().iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(v.len(), 2);
+        assert!(v.contains(&(0, 4)) && v.contains(&(2, 4)));
+        // Blocked pieces and pieces of the other player cannot be selected.
+        assert!(g.mv(1, 7).is_none());
+        assert!(g.mv(0, 2).is_none());
+        assert_eq!(g.moves().len(), 7);
+    }
 
-//    #[test]
-//    fn constructor() {
-//        let g = Board::new();
-//        assert_eq!(g.next_move, Player::Black);
-//        assert_eq!(g.winner, Player::None);
-//        assert_eq!(g.valid_pieces_to_move, vec![(1, 5), (3, 5), (5, 5), (7, 5)]);
-//    }
+    #[test]
+    fn constructor() {
+        let g = Board::new();
+        assert_eq!(g.player(), Player::Black);
+        assert_eq!(g.winner(), Player::None);
+        assert_eq!(g.movable_pieces(), vec![(1, 5), (3, 5), (5, 5), (7, 5)]);
+    }
 
     fn empty_board() -> Board {
-        let mut b = Board::new();
-        b.board = std::iter::repeat(Color::Empty).take(8 * 8).collect();
-        b
+        Board::from(std::iter::repeat(Color::Empty).take(8 * 8).collect())
     }
 
-    // TODO: reimplement test
-//    #[test]
-//    fn play1() {
-//        let mut g = empty_board();
-//        g.board[2 * 8 + 2] = Color::WhiteNormal;
-//        g.board[4 * 8 + 4] = Color::BlackNormal;
-//        g.next_move = Player::White;
-//        g.valid_pieces_to_move = vec![(2, 2)];
-//
-//        g.move_it(2, 2, 3, 3);
-//        assert_eq!(g.valid_pieces_to_move, vec![(4, 4)]);
-//        let v: Vec<(i32, i32)> = g.mv(4, 4).unwrap().iter().map(|ref p| (p.x, p.y)).collect();
-//        assert_eq!(v, vec![(2, 2)]);
-//
-//        g.move_it(4, 4, 2, 2);
-//        assert_eq!(g.winner, Player::Black);
-//    }
+    #[test]
+    fn play1() {
+        let mut v = vec![Color::Empty; 8 * 8];
+        v[2 * 8 + 2] = Color::WhiteNormal;
+        v[4 * 8 + 4] = Color::BlackNormal;
+        let mut g = Board::setup(v, Player::White);
+        assert_eq!(g.movable_pieces(), vec![(2, 2)]);
+
+        // The black man has to capture the white man which moved next to it.
+        g.move_it(2, 2, 3, 3);
+        assert_eq!(g.movable_pieces(), vec![(4, 4)]);
+        let v: Vec<(i32, i32)> = g.mv(4, 4).unwrap().iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(v, vec![(2, 2)]);
+        assert_eq!(g.moves().len(), 1);
+
+        g.move_it(4, 4, 2, 2);
+        assert_eq!(g.winner(), Player::Black);
+        assert!(g.moves().is_empty());
+    }
 
     #[test]
     fn positions() {
         let mut v: Vec<Color> = std::iter::repeat(Color::Empty).take(8 * 8).collect();
         v[0] = Color::WhiteNormal;
         v[9] = Color::WhiteNormal;
-        v[11] = Color::WhiteNormal;
+        v[11] = Color::WhiteDame;
         v[63] = Color::BlackNormal;
-        let mut g = Board::from(v);
-        assert_eq!(g.positions[0], (0, 0));
-        assert_eq!(g.positions[1], (1, 1));
-        assert_eq!(g.positions[2], (3, 1));
-        assert_eq!(g.positions[3], (7, 7));
-        g.remove_position(1, 1);
-        assert_eq!(g.positions[0], (0, 0));
-        assert_eq!(g.positions[1], (7, 7));
-        assert_eq!(g.positions[2], (3, 1));
-        g.remove_position(3, 1);
-        assert_eq!(g.positions[0], (0, 0));
-        assert_eq!(g.positions[1], (7, 7));
-    }
-        // TODO: test for moves_for
+        let g = Board::from(v);
+        assert_eq!(g.positions(Player::White), vec![(1, 1), (3, 1), (0, 0)]);
+        assert_eq!(g.positions(Player::Black), vec![(7, 7)]);
+        assert_eq!(g.count_normal(Player::White), 2);
+        assert_eq!(g.count_dame(Player::White), 1);
+        assert_eq!(g.count_normal(Player::Black), 1);
+        assert!(empty_board().positions(Player::White).is_empty());
+    }
+
+    #[test]
+    fn squares() {
+        assert_eq!(square(1, 7), Some(0));
+        assert_eq!(square(0, 6), Some(4));
+        assert_eq!(square(0, 0), Some(28));
+        assert_eq!(square(6, 0), Some(31));
+        assert_eq!(square(1, 0), None);
+        assert_eq!(square(8, 0), None);
+        for s in 0..32 {
+            let (x, y) = coords(s);
+            assert_eq!(square(x, y), Some(s));
+        }
+        let directions = [(-1, 1), (1, 1), (-1, -1), (1, -1)];
+        for s in 0..32 {
+            for (d, &(dx, dy)) in directions.iter().enumerate() {
+                let (x, y) = coords(s);
+                assert_eq!(STEP[s][d], square(x + dx, y + dy).map_or(NONE, |t| t as u8));
+                assert_eq!(JUMP[s][d], square(x + 2 * dx, y + 2 * dy).map_or(NONE, |t| t as u8));
+            }
+        }
+    }
 
     #[test]
     fn bits() {
         let mut v: Vec<Color> = std::iter::repeat(Color::Empty).take(8 * 8).collect();
         v[0] = Color::WhiteNormal;
         v[9] = Color::WhiteNormal;
-        v[11] = Color::WhiteNormal;
+        v[11] = Color::WhiteDame;
         v[63] = Color::BlackNormal;
+        v[1] = Color::BlackNormal; // a light square
         let mut g = Board::from(v);
 
-        assert!(g.board_bitmask & (1 << 0) > 0);
-        assert!(g.board_bitmask & (1 << 9) > 0);
-        assert!(g.board_bitmask & (1 << 11) > 0);
-        assert!(g.board_bitmask & (1 << 63) > 0);
+        assert_eq!(g.white, (1 << 28) | (1 << 24) | (1 << 25));
+        assert_eq!(g.black, 1 << 3);
+        assert_eq!(g.kings, 1 << 25);
         assert!(g.is_empty(0, 0) == false);
+        assert!(g.is_empty(2, 0));
+        assert!(g.is_empty(1, 0) == false);
+
+        g.put(28, Color::Empty);
+        assert_eq!(g.white, (1 << 24) | (1 << 25));
+        g.put(25, Color::BlackNormal);
+        assert_eq!(g.white, 1 << 24);
+        assert_eq!(g.black, (1 << 3) | (1 << 25));
+        assert_eq!(g.kings, 0);
+        assert_eq!(g.color(3, 1), Some(Color::BlackNormal));
+    }
 
-        g.clear_bit(0);
-        assert!(g.board_bitmask & (1 << 0) == 0);
-        assert!(g.board_bitmask & (1 << 9) > 0);
-        assert!(g.board_bitmask & (1 << 11) > 0);
-        assert!(g.board_bitmask & (1 << 63) > 0);
-        g.clear_bit(9);
-        assert!(g.board_bitmask & (1 << 0) == 0);
-        assert!(g.board_bitmask & (1 << 9) == 0);
-        assert!(g.board_bitmask & (1 << 11) > 0);
-        assert!(g.board_bitmask & (1 << 63) > 0);
-        g.clear_bit(63);
-        assert!(g.board_bitmask & (1 << 0) == 0);
-        assert!(g.board_bitmask & (1 << 9) == 0);
-        assert!(g.board_bitmask & (1 << 11) > 0);
-        assert!(g.board_bitmask & (1 << 63) == 0);
-        g.clear_bit(2);
-        assert!(g.board_bitmask & (1 << 2) == 0);
-        g.clear_bit(11);
-        assert!(g.board_bitmask == 0);
-    }
-
-}
\ No newline at end of file
+    #[test]
+    fn moves() {
+        // Number of positions after n moves from the start position. Up to a depth of 6 no
+        // multi-jump is possible, so that the numbers are the same as the published perft
+        // numbers.
+        fn walk(b: &Board, depth: usize) -> usize {
+            if depth == 0 || b.finished() {
+                return 1;
+            }
+            b.valid_moves().iter().map(|&(x, y, dx, dy)| {
+                let mut c = *b;
+                c.move_it(x, y, dx, dy);
+                walk(&c, depth - 1)
+            }).sum()
+        }
+        let n: Vec<usize> = (1..8).map(|d| walk(&Board::new(), d)).collect();
+        assert_eq!(n, vec![7, 49, 302, 1469, 7361, 36768, 179255]);
+
+        // A white man jumps twice and is crowned.
+        let mut v: Vec<Color> = std::iter::repeat(Color::Empty).take(8 * 8).collect();
+        v[3 * 8 + 3] = Color::WhiteNormal;
+        v[4 * 8 + 4] = Color::BlackNormal;
+        v[6 * 8 + 4] = Color::BlackNormal;
+        v[7 * 8 + 1] = Color::BlackNormal;
+        let mut g = Board::from(v);
+        g.next_move = Player::White;
+        g.update_valid_pieces_to_move();
+        g.rehash();
+        assert_eq!(g.valid_moves(), vec![(3, 3, 5, 5)]);
+        assert!(g.mv(3, 3).is_some());
+        assert!(g.mv(1, 7).is_none());
+        g.move_it(3, 3, 5, 5);
+        assert_eq!(g.player(), Player::White);
+        assert_eq!(g.movable_pieces(), vec![(5, 5)]);
+        g.move_it(5, 5, 3, 7);
+        assert_eq!(g.color(3, 7), Some(Color::WhiteDame));
+        assert_eq!(g.player(), Player::Black);
+        assert_eq!(g.get_last_moves(), vec![(3, 3, 5, 5), (5, 5, 3, 7)]);
+        assert_eq!(g.hash(), g.compute_hash());
+
+        // The move is ignored if it is not valid.
+        let h = g;
+        g.move_it(1, 7, 1, 6);
+        g.move_it(3, 7, 2, 6);
+        assert_eq!(g.to_json(), h.to_json());
+    }
+
+    #[test]
+    fn complete_moves() {
+        fn walk(b: &Board, depth: usize) -> usize {
+            if depth == 0 || b.finished() {
+                return 1;
+            }
+            b.moves().iter().map(|m| {
+                let mut c = *b;
+                assert!(c.make_move(m));
+                walk(&c, depth - 1)
+            }).sum()
+        }
+        // The published perft numbers count multi-jumps as one move.
+        let n: Vec<usize> = (1..8).map(|d| walk(&Board::new(), d)).collect();
+        assert_eq!(n, vec![7, 49, 302, 1469, 7361, 36768, 179740]);
+
+        // A white dame can capture the four black men around it clockwise or anticlockwise.
+        let mut v: Vec<Color> = std::iter::repeat(Color::Empty).take(8 * 8).collect();
+        v[2 * 8 + 2] = Color::WhiteDame;
+        for &(x, y) in &[(3, 3), (3, 5), (1, 3), (1, 5), (7, 7)] {
+            v[y * 8 + x] = Color::BlackNormal;
+        }
+        let mut g = Board::from(v);
+        g.next_move = Player::White;
+        g.update_valid_pieces_to_move();
+        g.rehash();
+        let m = g.moves();
+        assert_eq!(m.len(), 2);
+        assert_eq!(m[0].path(), vec![(2, 2), (0, 4), (2, 6), (4, 4), (2, 2)]);
+        assert_eq!(m[1].path(), vec![(2, 2), (4, 4), (2, 6), (0, 4), (2, 2)]);
+        assert_eq!(m[0].captured().len(), 4);
+
+        // Incomplete or invalid moves are not made.
+        let h = g;
+        assert!(!g.make_move(&Move::new(&[(2, 2), (4, 4)]).unwrap()));
+        assert!(!g.make_move(&Move::new(&[(2, 2), (4, 4), (2, 6), (0, 4)]).unwrap()));
+        assert!(!g.make_move(&Move::new(&[(2, 2), (3, 1)]).unwrap()));
+        assert_eq!(g.to_json(), h.to_json());
+        assert!(g.make(&Move::new(&[(2, 2), (4, 4), (2, 6), (0, 4)]).unwrap()).is_none());
+        assert!(g.make(&Move::new(&[(2, 2), (0, 4), (2, 6), (0, 4)]).unwrap()).is_none());
+        assert_eq!(g.to_json(), h.to_json());
+        assert_eq!(g.hash(), h.hash());
+
+        assert!(g.make_move(&m[1]));
+        assert_eq!(g.positions(Player::Black), vec![(7, 7)]);
+        assert_eq!(g.player(), Player::Black);
+        assert_eq!(g.get_last_moves().len(), 4);
+        assert_eq!(g.hash(), g.compute_hash());
+    }
+
+    #[test]
+    fn try_move() {
+        let mut g = Board::new();
+        assert_eq!(g.try_move(1, 5, 0, 5), Err(MoveError::NotOnBoard));
+        assert_eq!(g.try_move(1, 5, 0, 8), Err(MoveError::NotOnBoard));
+        assert_eq!(g.try_move(0, 2, 1, 3), Err(MoveError::NotYourPiece));
+        assert_eq!(g.try_move(1, 3, 0, 2), Err(MoveError::NotYourPiece));
+        assert_eq!(g.try_move(1, 7, 0, 6), Err(MoveError::Unreachable));
+        assert_eq!(g.try_move(1, 5, 1, 3), Err(MoveError::Unreachable));
+        let h = g.to_json();
+        assert_eq!(g.try_move(1, 5, 3, 3), Err(MoveError::Unreachable));
+        assert_eq!(g.to_json(), h);
+        assert_eq!(g.try_move(1, 5, 2, 4), Ok(MoveOutcome { captured: None, promoted: false, turn_over: true }));
+
+        // A double jump which crowns the black man; the other pieces have to wait.
+        let mut g = Board::from_fen("B:W18,26,30:B15,16").unwrap();
+        assert_eq!(g.try_move(6, 4, 7, 3), Err(MoveError::CaptureMandatory));
+        assert_eq!(g.try_move(4, 4, 5, 3), Err(MoveError::CaptureMandatory));
+        assert_eq!(g.try_move(4, 4, 2, 2), Ok(MoveOutcome { captured: Some((3, 3)), promoted: false, turn_over: false }));
+        assert_eq!(g.try_move(6, 4, 7, 3), Err(MoveError::MustContinueJump));
+        assert_eq!(g.try_move(2, 2, 1, 1), Err(MoveError::MustContinueJump));
+        assert_eq!(g.try_move(2, 2, 0, 0), Err(MoveError::Unreachable));
+        assert_eq!(g.try_move(2, 2, 4, 0), Ok(MoveOutcome { captured: Some((3, 1)), promoted: true, turn_over: true }));
+        assert_eq!(g.player(), Player::White);
+        assert_eq!(format!("{}", MoveError::CaptureMandatory), "capturing is mandatory");
+
+        let mut g = Board::from_fen("W:W18:B15").unwrap();
+        g.move_it(3, 3, 5, 5);
+        assert_eq!(g.try_move(5, 5, 4, 6), Err(MoveError::GameFinished));
+    }
+
+    #[test]
+    fn json() {
+        let mut g = Board::new();
+        g.move_it(1, 5, 0, 4);
+        let d: Board = json::decode(&g.to_json()).unwrap();
+        assert_eq!(d.to_json(), g.to_json());
+        assert_eq!(d.hash(), g.hash());
+        assert_eq!(d.get_last_moves(), vec![(1, 5, 0, 4)]);
+
+        let j = Json::from_str(&g.to_json()).unwrap();
+        assert_eq!(j["board"].as_array().unwrap().len(), 64);
+        assert_eq!(j["board"][2 * 8].as_string(), Some("WhiteNormal"));
+        assert_eq!(j["next_move"].as_string(), Some("White"));
+        assert_eq!(j["winner"].as_string(), Some("None"));
+        assert_eq!(j["move_no"].as_i64(), Some(1));
+
+        // Fields which are not required are optional.
+        let d: Board = json::decode(&format!("{{\"board\": {}, \"next_move\": \"White\", \"winner\": \"None\"}}", j["board"])).unwrap();
+        assert_eq!(d.valid_moves(), g.valid_moves());
+        assert!(json::decode::<Board>("{\"board\": [], \"next_move\": \"White\", \"winner\": \"None\"}").is_err());
+
+        // A board during a multi-jump.
+        let mut g = Board::from_fen("B:W18,26,30:B15,16").unwrap();
+        g.move_it(4, 4, 2, 2);
+        let d: Board = json::decode(&g.to_json()).unwrap();
+        assert_eq!(d.to_json(), g.to_json());
+
+        // Inconsistent boards are rejected.
+        let decode = |fen: &str, fields: &str| {
+            let (v, p) = Board::parse_fen(fen).unwrap();
+            let mut j = Json::from_str(&Board::setup(v, p).to_json()).unwrap();
+            let f = Json::from_str(&format!("{{{}}}", fields)).unwrap();
+            j.as_object_mut().unwrap().extend(f.as_object().unwrap().clone());
+            json::decode::<Board>(&j.to_string())
+        };
+        assert!(decode("B:W21:B1", "").is_ok());
+        assert!(decode("B:W21:B1", "\"next_move\": \"None\"").is_err());
+        assert!(decode("B:W21:B1", "\"winner\": \"White\"").is_err());
+        assert!(decode("B:W21:B1", "\"outcome\": \"BlackWins\", \"winner\": \"Black\"").is_err());
+        assert!(decode("B:W21:B1", "\"jumping\": [1, 7]").is_err());
+        assert!(decode("B:W21:B1", "\"jumping\": [0, 7]").is_err());
+        assert!(decode("B:W1:B21", "").is_err());
+        assert!(decode("B:W21-32:B1-13", "").is_err());
+        assert!(decode("B:W21:B29", "").is_err());
+        assert!(decode("B:W21:B18,22", "\"jumping\": [2, 2]").is_err());
+        assert!(decode("B:W25:B22", "\"jumping\": [2, 2]").is_ok());
+        let mut v = Json::from_str(&Board::new().to_json()).unwrap();
+        v.as_object_mut().unwrap().get_mut("board").unwrap().as_array_mut().unwrap()[1] = Json::String("WhiteNormal".to_string());
+        assert!(json::decode::<Board>(&v.to_string()).is_err());
+
+        // A finished game is not changed by a random move.
+        let mut g = decode("W:W21:B14,17", "\"outcome\": \"BlackWins\", \"winner\": \"Black\"").unwrap();
+        assert!(!ai::random(&mut g));
+        assert_eq!(g.other_player(Player::None), Player::None);
+    }
+
+    #[test]
+    fn validate() {
+        let mut g = Board::new();
+        assert!(g.validate().is_ok());
+        ai::random(&mut g);
+        assert!(g.validate().is_ok());
+
+        let fen = |f: &str| -> Vec<Color> { Board::parse_fen(f).unwrap().0 };
+        let b = Board::rebuild(&fen("W:W21:B25"), Player::White).unwrap();
+        assert_eq!(b.outcome(), GameOutcome::Ongoing);
+        assert_eq!(b.hash(), Board::from_fen("W:W21:B25").unwrap().hash());
+        let b = Board::rebuild(&fen("W:W29:B22,25"), Player::White).unwrap();
+        assert_eq!(b.outcome(), GameOutcome::BlackWins);
+
+        // All problems are reported.
+        let mut v = fen("B:W1,2,21-28,30-32:B29");
+        v[1] = Color::BlackNormal;
+        let e = Board::rebuild(&v, Player::None).unwrap_err();
+        assert_eq!(e, vec![
+            "a piece is on the light square (1, 0)",
+            "there is no player to move",
+            "White has more than 12 pieces",
+            "White has a man on square 1 on the row on which it is crowned",
+            "White has a man on square 2 on the row on which it is crowned",
+            "Black has a man on square 29 on the row on which it is crowned",
+        ]);
+        assert!(Board::rebuild(&v[1..], Player::White).is_err());
+
+        // Bitboards and derived fields which disagree.
+        let mut b = Board::new();
+        b.black |= 1 << 31;
+        b.kings |= 1 << 16;
+        let e = b.validate().unwrap_err();
+        assert_eq!(e, vec![
+            "square 32 is occupied by both colors",
+            "the dame on square 17 has no color",
+            "Black has more than 12 pieces",
+            "Black has a man on square 32 on the row on which it is crowned"
+        ]);
+        let mut b = Board::new();
+        b.hash ^= 1;
+        assert_eq!(b.validate().unwrap_err(), vec!["the hash does not match the position"]);
+        let mut b = Board::new();
+        b.movable = 0;
+        assert_eq!(b.validate().unwrap_err(), vec![
+            "the movable pieces do not match the position",
+            "the player to move cannot move but the game is not finished"
+        ]);
+    }
+
+    #[test]
+    fn symmetry() {
+        let b = Board::new();
+        assert_eq!(b.flip_colors().to_fen(), "W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12");
+        assert_eq!(b.canonical().0.hash(), b.hash());
+        assert_eq!(b.flip_colors().canonical().0.hash(), b.hash());
+        assert!(b.flip_colors().canonical().1);
+
+        // The flipped board has the mirrored moves and the same outcome with the colors swapped.
+        let flip = |v: Vec<(i32, i32, i32, i32)>| -> Vec<(i32, i32, i32, i32)> {
+            let mut v: Vec<_> = v.iter().map(|&(x, y, dx, dy)| (7 - x, 7 - y, 7 - dx, 7 - dy)).collect();
+            v.sort();
+            v
+        };
+        for _ in 0..20 {
+            let mut g = Board::new();
+            while ai::random(&mut g) {
+                let f = g.flip_colors();
+                assert!(f.validate().is_ok());
+                assert_eq!(f.player(), g.other_player(g.player()));
+                assert_eq!(f.winner(), g.other_player(g.winner()));
+                let (mut v, mut l) = (g.valid_moves(), g.get_last_moves());
+                v.sort();
+                l.sort();
+                assert_eq!(flip(f.valid_moves()), v);
+                assert_eq!(flip(f.get_last_moves()), l);
+                assert_eq!(f.flip_colors().hash(), g.hash());
+                assert_eq!(f.canonical().0.hash(), g.canonical().0.hash());
+                assert_eq!(g.canonical().0.player(), Player::Black);
+            }
+        }
+    }
+
+    #[test]
+    fn draws() {
+        let mut v: Vec<Color> = std::iter::repeat(Color::Empty).take(8 * 8).collect();
+        v[0] = Color::WhiteDame;
+        v[7 * 8 + 7] = Color::BlackDame;
+        v[7 * 8 + 1] = Color::BlackNormal;
+        let start = Board::from(v);
+        let shuffle = [(7, 7, 6, 6), (0, 0, 1, 1), (6, 6, 7, 7), (1, 1, 0, 0)];
+
+        // The board counts the moves, the repetitions are detected by Positions.
+        let mut g = start;
+        for (i, &(x, y, dx, dy)) in shuffle.iter().chain(shuffle.iter()).enumerate() {
+            g.move_it(x, y, dx, dy);
+            assert_eq!(g.move_no, i as i32 + 1);
+            g = json::decode(&g.to_json()).unwrap();
+        }
+        assert_eq!(g.reversible_moves(), 8);
+        assert!(!g.finished());
+        g.set_repetition();
+        assert_eq!(g.outcome(), GameOutcome::Draw(DrawReason::Repetition));
+        assert_eq!(g.winner(), Player::None);
+        assert!(g.valid_moves().is_empty());
+        g.move_it(7, 7, 6, 6);
+        assert_eq!(g.move_no, 8);
+        let j = Json::from_str(&g.to_json()).unwrap();
+        assert_eq!(j["finished"].as_boolean(), Some(true));
+        assert_eq!(j["winner"].as_string(), Some("None"));
+
+        // Two moves of each player without a capture or a move of a man.
+        let mut g = start;
+        g.set_move_rule(2);
+        g.move_it(7, 7, 6, 6);
+        g.move_it(0, 0, 1, 1);
+        g.move_it(6, 6, 5, 5);
+        assert!(!g.finished());
+        g.move_it(1, 1, 2, 0);
+        assert_eq!(g.outcome(), GameOutcome::Draw(DrawReason::MoveRule));
+        assert!(g.valid_moves().is_empty());
+
+        // A move of a man starts the counting again.
+        let mut g = start;
+        g.set_move_rule(2);
+        g.move_it(7, 7, 6, 6);
+        g.move_it(0, 0, 1, 1);
+        g.move_it(1, 7, 0, 6);
+        g.move_it(1, 1, 0, 0);
+        g.move_it(6, 6, 7, 7);
+        g.move_it(0, 0, 1, 1);
+        assert!(!g.finished());
+        g.move_it(7, 7, 6, 6);
+        assert_eq!(g.outcome(), GameOutcome::Draw(DrawReason::MoveRule));
+    }
+
+    #[test]
+    fn hash() {
+        for _ in 0..20 {
+            let mut g = Board::new();
+            while !g.finished() && g.move_no < 200 {
+                ai::random(&mut g);
+                assert_eq!(g.hash(), g.compute_hash());
+            }
+        }
+
+        // The same position reached by different move orders has the same hash.
+        let mut a = Board::new();
+        a.move_it(1, 5, 0, 4);
+        a.move_it(6, 2, 7, 3);
+        a.move_it(3, 5, 4, 4);
+        let mut b = Board::new();
+        b.move_it(3, 5, 4, 4);
+        b.move_it(6, 2, 7, 3);
+        b.move_it(1, 5, 0, 4);
+        assert_eq!(a.move_no, 3);
+        assert_eq!((a.white, a.black, a.kings), (b.white, b.black, b.kings));
+        assert_eq!(a.hash(), b.hash());
+        assert!(a.hash() != Board::new().hash());
+    }
+
+    #[test]
+    fn make_unmake() {
+        let (mut captures, mut dames, mut promotions) = (0, 0, 0);
+        for _ in 0..20 {
+            let mut g = Board::new();
+            while !g.finished() && g.move_no < 300 {
+                g.clear_last_moves();
+                let v = g.moves();
+                for m in &v {
+                    let mut a = g;
+                    let mut b = g;
+                    let u = a.make(m).unwrap();
+                    assert!(b.make_move(m));
+                    assert_eq!(a.to_json(), b.to_json());
+                    assert_eq!(a.hash(), b.hash());
+                    a.unmake(u);
+                    assert_eq!(a.to_json(), g.to_json());
+                    assert_eq!(a.hash(), g.hash());
+                    assert_eq!(a.reversible_moves(), g.reversible_moves());
+                    captures += m.is_capture() as usize;
+                    dames += u.kings.count_ones();
+                    promotions += u.promoted as usize;
+                }
+                g.make_move(thread_rng().choose(&v).unwrap());
+            }
+        }
+        // Captures of men and dames and promotions have been taken back.
+        assert!(captures > 0 && dames > 0 && promotions > 0);
+    }
+
+    #[test]
+    fn size() {
+        // The board is copied for each move of the search.
+        assert!(std::mem::size_of::<Board>() <= 128);
+    }
+}
@@ -0,0 +1,259 @@
+use std;
+use std::str::FromStr;
+
+use board::board::{Board, Color, Player, coords, square};
+
+// Positions as text: the FEN of the Portable Draughts Notation, e.g. "B:W21,22,K30:B1,2,3",
+// and a diagram of the board which is printed by Display. Both are parsed by FromStr. Squares
+// are given by the standard numbers 1 - 32, see Board. Neither format describes a piece which
+// has to continue a jump, so positions are always at the start of a turn. Parsed positions
+// are validated like the boards of the Position builder.
+
+const FILES: &'static str = "abcdefgh";
+
+fn player(s: &str) -> Option<Player> {
+    match s.chars().next() {
+        Some('W') => Some(Player::White),
+        Some('B') => Some(Player::Black),
+        _ => None
+    }
+}
+
+fn name(p: Player) -> &'static str {
+    if p == Player::White { "White" } else { "Black" }
+}
+
+fn piece(p: Player, king: bool) -> Color {
+    match (p, king) {
+        (Player::White, false) => Color::WhiteNormal,
+        (Player::White, true) => Color::WhiteDame,
+        (_, false) => Color::BlackNormal,
+        (_, true) => Color::BlackDame,
+    }
+}
+
+// Returns the character of a piece in a diagram; dames are upper case.
+fn symbol(c: Color) -> char {
+    match c {
+        Color::WhiteNormal => 'w',
+        Color::WhiteDame => 'W',
+        Color::BlackNormal => 'b',
+        Color::BlackDame => 'B',
+        Color::Empty => '.'
+    }
+}
+
+fn parse_square(s: &str) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(n) if (1..33).contains(&n) => Ok(n - 1),
+        _ => Err(format!("invalid square: {}", s))
+    }
+}
+
+// Puts piece c on the square with index s (0 - 31) of v which is indexed by y * 8 + x.
+fn put(v: &mut [Color], s: usize, c: Color) -> Result<(), String> {
+    let (x, y) = coords(s);
+    let i = (y * 8 + x) as usize;
+    if v[i] != Color::Empty {
+        return Err(format!("square {} is given twice", s + 1));
+    }
+    v[i] = c;
+    Ok(())
+}
+
+impl Board {
+
+    // Parses a position in FEN, e.g. "W:W21,22,K30:B1,2,3". The first field is the player to
+    // move, the other fields list the squares of the pieces of one color. Dames are prefixed
+    // with K and ranges such as "B1-12" are accepted.
+    pub fn from_fen(fen: &str) -> Result<Board, String> {
+        let (v, next) = try!(Board::parse_fen(fen));
+        Board::rebuild(&v, next).map_err(|e| e.join("; "))
+    }
+
+    // Parses a position in FEN into its 64 squares, indexed by y * 8 + x, and the player to
+    // move without validating it, e.g. for a position which cannot occur in a game.
+    pub fn parse_fen(fen: &str) -> Result<(Vec<Color>, Player), String> {
+        let fen = fen.trim().trim_end_matches('.');
+        let fields: Vec<&str> = fen.split(':').map(|f| f.trim()).collect();
+        let next = match player(fields[0]) {
+            Some(p) if fields[0].len() == 1 => p,
+            _ => return Err(format!("invalid player to move in FEN: {}", fen))
+        };
+        if fields.len() > 3 {
+            return Err(format!("too many fields in FEN: {}", fen));
+        }
+
+        let mut v = vec![Color::Empty; 64];
+        for f in &fields[1..] {
+            let p = match player(f) {
+                Some(p) => p,
+                _ => return Err(format!("invalid field in FEN: {}", f))
+            };
+            for sq in f[1..].split(',').filter(|s| !s.trim().is_empty()) {
+                let sq = sq.trim();
+                let king = sq.starts_with('K');
+                let sq = if king { &sq[1..] } else { sq };
+                let mut r = sq.splitn(2, '-');
+                let a = try!(parse_square(r.next().unwrap()));
+                let b = match r.next() {
+                    Some(b) => try!(parse_square(b)),
+                    None => a
+                };
+                if b < a {
+                    return Err(format!("invalid range: {}", sq));
+                }
+                for s in a..b + 1 {
+                    try!(put(&mut v, s, piece(p, king)));
+                }
+            }
+        }
+        Ok((v, next))
+    }
+
+    // Returns the position in FEN with the squares in ascending order, e.g. "B:W21,22,K30:B1,2".
+    pub fn to_fen(&self) -> String {
+        let field = |p: Player| -> String {
+            let mut v: Vec<(usize, bool)> = self.positions(p).into_iter().map(|(x, y)| {
+                let c = self.color(x, y).unwrap();
+                (square(x, y).unwrap(), c == Color::WhiteDame || c == Color::BlackDame)
+            }).collect();
+            v.sort();
+            let s: Vec<String> = v.iter().map(|&(s, k)| format!("{}{}", if k { "K" } else { "" }, s + 1)).collect();
+            s.join(",")
+        };
+        let p = if self.player() == Player::White { "W" } else { "B" };
+        format!("{}:W{}:B{}", p, field(Player::White), field(Player::Black))
+    }
+
+    // Parses a diagram as printed by Display. Each row starts with its number followed by the
+    // eight squares; w and b are men, W and B dames and . an empty square. The player to move
+    // is given by a line "White to move" or "Black to move"; without it black is to move.
+    pub fn from_diagram(s: &str) -> Result<Board, String> {
+        let mut v = vec![Color::Empty; 64];
+        let mut rows = [false; 8];
+        let mut next = Player::Black;
+        for line in s.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            let t: Vec<&str> = line.split_whitespace().collect();
+            if line.ends_with("to move") {
+                next = match t[0] {
+                    "White" => Player::White,
+                    "Black" => Player::Black,
+                    _ => return Err(format!("invalid player to move: {}", line))
+                };
+                continue;
+            }
+            let y = match t[0].parse::<usize>() {
+                Ok(n) if (1..9).contains(&n) => n - 1,
+                _ if t.len() == 8 && t.iter().zip(FILES.chars()).all(|(a, b)| a.len() == 1 && a.starts_with(b)) => continue,
+                _ => return Err(format!("invalid row: {}", line))
+            };
+            if t.len() != 9 || rows[y] {
+                return Err(format!("invalid row: {}", line));
+            }
+            rows[y] = true;
+            for (x, sq) in t[1..].iter().enumerate() {
+                v[y * 8 + x] = match *sq {
+                    "." => Color::Empty,
+                    "w" => Color::WhiteNormal,
+                    "W" => Color::WhiteDame,
+                    "b" => Color::BlackNormal,
+                    "B" => Color::BlackDame,
+                    _ => return Err(format!("invalid square {} in row: {}", sq, line))
+                };
+                if v[y * 8 + x] != Color::Empty && square(x as i32, y as i32).is_none() {
+                    return Err(format!("piece on a light square in row: {}", line));
+                }
+            }
+        }
+        if rows.iter().any(|&r| !r) {
+            return Err("the diagram must have 8 rows".to_string());
+        }
+        Board::rebuild(&v, next).map_err(|e| e.join("; "))
+    }
+}
+
+// Prints a diagram of the board with white at the bottom, e.g. for the start position
+//
+//   8  . b . b . b . b
+//   ...
+//   1  w . w . w . w .
+//      a b c d e f g h
+//   Black to move
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for y in (0..8).rev() {
+            let row: Vec<String> = (0..8).map(|x| symbol(self.color(x, y).unwrap()).to_string()).collect();
+            try!(writeln!(f, "{}  {}", y + 1, row.join(" ")));
+        }
+        let files: Vec<String> = FILES.chars().map(|c| c.to_string()).collect();
+        try!(writeln!(f, "   {}", files.join(" ")));
+        write!(f, "{} to move", name(self.player()))
+    }
+}
+
+// Parses a position in FEN or a diagram.
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Board, String> {
+        if s.contains(':') {
+            Board::from_fen(s)
+        } else {
+            Board::from_diagram(s)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use board::board::{Board, Color, Player};
+
+    #[test]
+    fn fen() {
+        let b = Board::new();
+        let fen = "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12";
+        assert_eq!(b.to_fen(), fen);
+        assert_eq!(Board::from_fen(fen).unwrap().to_json(), b.to_json());
+        assert_eq!(Board::from_fen("B:W21-32:B1-12.").unwrap().to_json(), b.to_json());
+        assert_eq!(fen.parse::<Board>().unwrap().hash(), b.hash());
+
+        let b = Board::from_fen("W:W21,22,K30:B1,2,3").unwrap();
+        assert_eq!(b.player(), Player::White);
+        assert_eq!(b.color(1, 7), Some(Color::BlackNormal));
+        assert_eq!(b.color(2, 0), Some(Color::WhiteDame));
+        assert_eq!(b.to_fen(), "W:W21,22,K30:B1,2,3");
+        assert_eq!(Board::from_fen("W:B1:W").unwrap().to_fen(), "W:W:B1");
+
+        assert!(Board::from_fen("X:W21:B1").is_err());
+        assert!(Board::from_fen("W:W21:B21").is_err());
+        assert!(Board::from_fen("W:W33:B1").is_err());
+        assert!(Board::from_fen("W:W21:X1").is_err());
+        assert!(Board::from_fen("W:W5-1:B10").is_err());
+        assert!(Board::from_fen("W:W21:B1:W2").is_err());
+
+        // Positions which cannot occur in a game are rejected.
+        assert!(Board::from_fen("B:W1:B10").unwrap_err().contains("row on which it is crowned"));
+        assert!(Board::from_fen("B:W13-32:B1").unwrap_err().contains("more than 12 pieces"));
+    }
+
+    #[test]
+    fn diagram() {
+        let b = Board::new();
+        let s = format!("{}", b);
+        assert!(s.starts_with("8  . b . b . b . b\n"));
+        assert!(s.ends_with("1  w . w . w . w .\n   a b c d e f g h\nBlack to move"));
+        assert_eq!(s.parse::<Board>().unwrap().to_json(), b.to_json());
+
+        let b = Board::from_fen("W:WK6,22:BK27,1").unwrap();
+        let d = Board::from_diagram(&format!("{}", b)).unwrap();
+        assert_eq!(d.to_fen(), "W:WK6,22:B1,K27");
+        assert_eq!(d.hash(), b.hash());
+
+        assert!(Board::from_diagram("8  . b . b . b . b").is_err());
+        assert!(Board::from_diagram(&s.replace(". b . b . b . b", "b . b . b . b .")).is_err());
+        assert!(Board::from_diagram(&s.replace("Black to move", "Red to move")).is_err());
+        assert!(Board::from_diagram(&s.replace("8  . b . b . b . b", "8  . w . b . b . b")).is_err());
+    }
+}
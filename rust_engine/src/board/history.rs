@@ -0,0 +1,277 @@
+use rustc_serialize::json;
+use rustc_serialize::json::{Json, ToJson};
+use std;
+
+use board::board::{Board, GameOutcome, Undo};
+use board::moves::Move;
+
+// The positions after each complete move of a game, with which repetitions are detected. The
+// board only counts the moves since the last capture or move of a man, so only that many of the
+// latest positions are compared. Only the upper 32 bits of the hashes are kept.
+#[derive(Clone, Debug)]
+pub struct Positions {
+    keys: Vec<u32>,
+}
+
+fn key(b: &Board) -> u32 {
+    (b.hash() >> 32) as u32
+}
+
+impl Positions {
+
+    // Starts with the position of b.
+    pub fn new(b: &Board) -> Positions {
+        Positions {
+            keys: vec![key(b)],
+        }
+    }
+
+    // Adds the position of b after a complete move. If the position has occurred three times
+    // with the same player to move, the game on b is a draw.
+    pub fn push(&mut self, b: &mut Board) {
+        self.keys.push(key(b));
+        if self.repetitions(b) >= 3 {
+            b.set_repetition();
+        }
+    }
+
+    // Removes the latest position, e.g. when its move is taken back. The first position is kept.
+    pub fn pop(&mut self) {
+        if self.keys.len() > 1 {
+            self.keys.pop();
+        }
+    }
+
+    // Returns how often the latest position, which is the position of b, has occurred with the
+    // same player to move.
+    pub fn repetitions(&self, b: &Board) -> usize {
+        let k = self.recent(b);
+        k.iter().rev().step_by(2).filter(|&&x| x == k[k.len() - 1]).count()
+    }
+
+    // The positions since the last capture or move of a man on b.
+    fn recent(&self, b: &Board) -> &[u32] {
+        let n = std::cmp::min(b.reversible_moves() + 1, self.keys.len());
+        &self.keys[self.keys.len() - n..]
+    }
+
+    // Returns the JSON of b with the positions which can occur again as "positions", so that
+    // repetitions are still detected after the board has been sent to the server and back.
+    pub fn to_json(&self, b: &Board) -> String {
+        let mut j = Json::from_str(&b.to_json()).unwrap();
+        if let Json::Object(ref mut o) = j {
+            o.insert("positions".to_string(), self.recent(b).to_vec().to_json());
+        }
+        j.to_string()
+    }
+
+    // Reads the positions from the JSON s of the board b as written by to_json. Without them
+    // only the position of b is known.
+    pub fn from_json(s: &str, b: &Board) -> Result<Positions, String> {
+        let j = try!(Json::from_str(s).map_err(|e| format!("{}", e)));
+        let keys: Vec<u32> = match j.find("positions") {
+            Some(p) => try!(json::decode(&p.to_string()).map_err(|e| format!("invalid positions: {}", e))),
+            None => return Ok(Positions::new(b))
+        };
+        // During a multi-jump the positions end before the turn.
+        match keys.last() {
+            None => Err("there are no positions".to_string()),
+            Some(&k) if b.jumping().is_none() && k != key(b) => Err("the last position does not match the board".to_string()),
+            _ => Ok(Positions {
+                keys: keys,
+            })
+        }
+    }
+}
+
+// A board together with the moves made on it, which can be taken back one after another, and
+// the positions of the game.
+#[derive(Clone, Debug)]
+pub struct History {
+    start: Board,
+    board: Board,
+    undo: Vec<(Move, Undo)>,
+    positions: Positions,
+}
+
+impl History {
+
+    pub fn new(b: Board) -> History {
+        History {
+            start: b,
+            board: b,
+            undo: vec![],
+            positions: Positions::new(&b),
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn positions(&self) -> &Positions {
+        &self.positions
+    }
+
+    // Returns the board the history was created with.
+    pub fn start(&self) -> Board {
+        self.start
+    }
+
+    // Returns the moves made since the history was created.
+    pub fn moves(&self) -> Vec<Move> {
+        self.undo.iter().map(|&(m, _)| m).collect()
+    }
+
+    // Makes the move m. Returns false if the move is not valid.
+    pub fn make(&mut self, m: &Move) -> bool {
+        match self.board.make(m) {
+            Some(u) => {
+                self.positions.push(&mut self.board);
+                self.undo.push((*m, u));
+                true
+            },
+            _ => false
+        }
+    }
+
+    // Takes back the last move and returns it or None if there is no move to take back. Unlike
+    // Board::unmake, the last moves of the board are restored as well.
+    pub fn unmake(&mut self) -> Option<Move> {
+        self.undo.pop().map(|(m, u)| {
+            self.board.unmake(u);
+            match self.undo.last() {
+                Some(&(p, _)) => self.board.set_last_moves(&p),
+                None => self.board = self.start
+            }
+            self.positions.pop();
+            m
+        })
+    }
+
+    // Takes back the last n moves or all moves if less than n moves were made. Returns the number
+    // of moves taken back.
+    pub fn undo(&mut self, n: usize) -> usize {
+        (0..n).take_while(|_| self.unmake().is_some()).count()
+    }
+
+    // Returns true if the game is a draw by repetition.
+    pub fn repeated(&self) -> bool {
+        self.board.outcome() != GameOutcome::Ongoing && self.positions.repetitions(&self.board) >= 3
+    }
+
+    // Returns the board and the positions as JSON, see Positions::to_json. The moves are not
+    // included and cannot be taken back after the history has been read again.
+    pub fn to_json(&self) -> String {
+        self.positions.to_json(&self.board)
+    }
+
+    pub fn from_json(s: &str) -> Result<History, String> {
+        let b: Board = try!(json::decode(s).map_err(|e| format!("{}", e)));
+        let p = try!(Positions::from_json(s, &b));
+        Ok(History {
+            start: b,
+            board: b,
+            undo: vec![],
+            positions: p,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+    use rustc_serialize::json::{Json, ToJson};
+    use std;
+    use board::board::{Board, Color, DrawReason, GameOutcome, Player};
+    use board::history::History;
+    use board::moves::Move;
+
+    #[test]
+    fn undo() {
+        let (mut captures, mut dames) = (0, 0);
+        for _ in 0..20 {
+            let mut h = History::new(Board::new());
+            let mut boards = vec![];
+            while !h.board().finished() && h.moves().len() < 200 {
+                boards.push((h.board().to_json(), h.board().hash()));
+                let m = *thread_rng().choose(&h.board().moves()).unwrap();
+                assert!(h.make(&m));
+                if m.is_capture() {
+                    captures += 1;
+                }
+            }
+            dames += h.board().count_dame(Player::White) + h.board().count_dame(Player::Black);
+
+            assert_eq!(h.undo(2), 2);
+            boards.pop();
+            boards.pop();
+            while let Some((b, hash)) = boards.pop() {
+                assert!(h.unmake().is_some());
+                assert_eq!(h.board().to_json(), b);
+                assert_eq!(h.board().hash(), hash);
+            }
+            assert!(h.unmake().is_none());
+            assert_eq!(h.undo(5), 0);
+            assert!(h.moves().is_empty());
+        }
+
+        // captures and promotions have been taken back
+        assert!(captures > 0 && dames > 0);
+
+        let mut h = History::new(Board::new());
+        let m = h.board().moves()[0];
+        assert!(h.make(&m));
+        assert!(!h.make(&m));
+        assert_eq!(h.moves(), vec![m]);
+    }
+
+    #[test]
+    fn repetition() {
+        let mut v: Vec<Color> = std::iter::repeat(Color::Empty).take(8 * 8).collect();
+        v[0] = Color::WhiteDame;
+        v[7 * 8 + 7] = Color::BlackDame;
+        v[7 * 8 + 1] = Color::BlackNormal;
+        let start = Board::from(v);
+        let shuffle = [(7, 7, 6, 6), (0, 0, 1, 1), (6, 6, 7, 7), (1, 1, 0, 0)];
+        let play = |h: &mut History, &(x, y, dx, dy): &(i32, i32, i32, i32)| {
+            assert!(h.make(&Move::new(&[(x, y), (dx, dy)]).unwrap()));
+        };
+
+        // The start position occurs for the third time after two rounds, also when the game is
+        // sent as JSON after each move.
+        let mut h = History::new(start);
+        for m in shuffle.iter().chain(shuffle.iter()) {
+            assert!(!h.board().finished());
+            play(&mut h, m);
+            h = History::from_json(&h.to_json()).unwrap();
+        }
+        assert!(h.repeated());
+        assert_eq!(h.board().outcome(), GameOutcome::Draw(DrawReason::Repetition));
+
+        // Taking back the last move continues the game.
+        let mut h = History::new(start);
+        for m in shuffle.iter().chain(shuffle.iter()) {
+            play(&mut h, m);
+        }
+        assert!(h.repeated());
+        assert!(h.unmake().is_some());
+        assert!(!h.repeated());
+        assert!(!h.board().finished());
+
+        // A move of a man starts again.
+        play(&mut h, &(1, 1, 2, 2));
+        play(&mut h, &(1, 7, 2, 6));
+        assert_eq!(h.board().reversible_moves(), 0);
+        assert_eq!(h.positions().repetitions(h.board()), 1);
+
+        // The positions have to end with the board.
+        let j = Json::from_str(&h.to_json()).unwrap();
+        let mut o = j.as_object().unwrap().clone();
+        assert!(History::from_json(&Json::Object(o.clone()).to_string()).is_ok());
+        o.insert("positions".to_string(), vec![1].to_json());
+        assert!(History::from_json(&Json::Object(o.clone()).to_string()).is_err());
+        o.insert("positions".to_string(), Vec::<u32>::new().to_json());
+        assert!(History::from_json(&Json::Object(o).to_string()).is_err());
+    }
+}
@@ -1,2 +1,10 @@
 pub mod board;
-pub mod point;
\ No newline at end of file
+pub mod fen;
+pub mod history;
+pub mod moves;
+pub mod notation;
+pub mod pdn;
+pub mod perft;
+pub mod point;
+pub mod position;
+pub mod rules;
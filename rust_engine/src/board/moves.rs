@@ -0,0 +1,133 @@
+use std;
+
+use board::board::{coords, square};
+use board::notation::algebraic;
+
+// Maximum number of hops of a move. Each hop of a multi-jump captures one of the twelve pieces
+// of the opponent.
+pub const MAX_HOPS: usize = 12;
+
+// A complete move of a player, i.e. a step or a whole sequence of jumps. The squares are
+// numbered from 0 to 31 as the bits of the bitboards of Board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    path: [u8; MAX_HOPS + 1],   // squares visited by the piece; the first one is the source
+    len: usize,                 // number of squares in path
+    captured: [u8; MAX_HOPS],   // squares of the captured pieces
+    n_captured: usize,
+}
+
+impl Move {
+
+    // Creates a move from the squares (x, y) visited by the piece. Returns None if a square is
+    // not a dark square of the board, if steps and jumps are mixed or if the path is too short or
+    // too long. Whether the move is valid on a board is not checked.
+    pub fn new(path: &[(i32, i32)]) -> Option<Move> {
+        if path.len() < 2 || path.len() > MAX_HOPS + 1 {
+            return None;
+        }
+        let mut m = Move {
+            path: [0; MAX_HOPS + 1],
+            len: 0,
+            captured: [0; MAX_HOPS],
+            n_captured: 0,
+        };
+        for (i, &(x, y)) in path.iter().enumerate() {
+            let s = match square(x, y) {
+                Some(s) => s,
+                _ => return None
+            };
+            if i > 0 {
+                let (px, py) = path[i - 1];
+                let (dx, dy) = (x - px, y - py);
+                let step = dx.abs() == 1 && dy.abs() == 1 && path.len() == 2;
+                let jump = dx.abs() == 2 && dy.abs() == 2;
+                if !step && !jump {
+                    return None;
+                }
+                if jump {
+                    m.captured[m.n_captured] = square(px + dx / 2, py + dy / 2).unwrap() as u8;
+                    m.n_captured += 1;
+                }
+            }
+            m.path[i] = s as u8;
+            m.len += 1;
+        }
+        Some(m)
+    }
+
+    // Returns a copy of the move extended by a jump to (x, y).
+    pub fn extend(&self, x: i32, y: i32) -> Option<Move> {
+        let mut p = self.path();
+        p.push((x, y));
+        Move::new(&p)
+    }
+
+    // Returns the squares (x, y) visited by the piece including the source and the destination.
+    pub fn path(&self) -> Vec<(i32, i32)> {
+        self.path.iter().take(self.len).map(|&s| coords(s as usize)).collect()
+    }
+
+    // Returns the squares (0 - 31) visited by the piece like path.
+    pub fn squares(&self) -> &[u8] {
+        &self.path[..self.len]
+    }
+
+    // Returns the squares (x, y) of the captured pieces.
+    pub fn captured(&self) -> Vec<(i32, i32)> {
+        self.captured.iter().take(self.n_captured).map(|&s| coords(s as usize)).collect()
+    }
+
+    // Returns the single steps or jumps of the move as (x, y, dx, dy) as accepted by
+    // Board::move_it.
+    pub fn hops(&self) -> Vec<(i32, i32, i32, i32)> {
+        let p = self.path();
+        p.windows(2).map(|w| (w[0].0, w[0].1, w[1].0, w[1].1)).collect()
+    }
+
+    pub fn src(&self) -> (i32, i32) {
+        coords(self.path[0] as usize)
+    }
+
+    pub fn dst(&self) -> (i32, i32) {
+        coords(self.path[self.len - 1] as usize)
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.n_captured > 0
+    }
+}
+
+// Prints the move in algebraic notation, e.g. "b6-a5" or "c3xe5xc7", see board::notation.
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", algebraic(self))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use board::moves::Move;
+
+    #[test]
+    fn new() {
+        let m = Move::new(&[(1, 5), (0, 4)]).unwrap();
+        assert_eq!(m.src(), (1, 5));
+        assert_eq!(m.dst(), (0, 4));
+        assert!(!m.is_capture());
+        assert_eq!(format!("{}", m), "b6-a5");
+
+        let m = Move::new(&[(2, 2), (4, 4), (2, 6)]).unwrap();
+        assert_eq!(m.captured(), vec![(3, 3), (3, 5)]);
+        assert_eq!(m.hops(), vec![(2, 2, 4, 4), (4, 4, 2, 6)]);
+        assert_eq!(m, Move::new(&[(2, 2), (4, 4)]).unwrap().extend(2, 6).unwrap());
+        assert_eq!(format!("{}", m), "c3xe5xc7");
+
+        assert!(Move::new(&[(2, 2)]).is_none());
+        assert!(Move::new(&[(2, 2), (3, 2)]).is_none());
+        assert!(Move::new(&[(2, 2), (5, 5)]).is_none());
+        assert!(Move::new(&[(2, 2), (3, 3), (5, 5)]).is_none());
+        assert!(Move::new(&[(2, 2), (1, 8)]).is_none());
+    }
+}
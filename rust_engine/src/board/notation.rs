@@ -0,0 +1,153 @@
+use board::board::{Board, coords, square};
+use board::moves::Move;
+
+// Moves as text. The numeric notation of American checkers uses the standard numbers 1 - 32 of
+// the dark squares, see Board, e.g. "11-15" for a step and "22x15x8" for a double jump. The
+// algebraic notation names the squares by file a - h and row 1 - 8 with white at the bottom,
+// e.g. "c3-d4" or "c3xe5xc7". Both are resolved against the legal moves of a board.
+
+const FILES: &'static str = "abcdefgh";
+
+// Returns the standard number (1 - 32) of the dark square (x, y).
+pub fn number(x: i32, y: i32) -> Option<usize> {
+    square(x, y).map(|s| s + 1)
+}
+
+// Returns the square (x, y) with the standard number n (1 - 32).
+pub fn from_number(n: usize) -> Option<(i32, i32)> {
+    if (1..33).contains(&n) {
+        Some(coords(n - 1))
+    } else {
+        None
+    }
+}
+
+fn separator(m: &Move) -> &'static str {
+    if m.is_capture() { "x" } else { "-" }
+}
+
+// Returns the move in numeric notation, e.g. "11-15" or "22x15x8".
+pub fn numeric(m: &Move) -> String {
+    let s: Vec<String> = m.path().iter().map(|&(x, y)| number(x, y).unwrap().to_string()).collect();
+    s.join(separator(m))
+}
+
+// Returns the name of the square (x, y) in algebraic notation, e.g. "c3".
+pub fn square_name(x: i32, y: i32) -> String {
+    format!("{}{}", &FILES[x as usize..x as usize + 1], y + 1)
+}
+
+// Returns the move in algebraic notation, e.g. "b6-a5" or "c3xe5xc7".
+pub fn algebraic(m: &Move) -> String {
+    let s: Vec<String> = m.path().iter().map(|&(x, y)| square_name(x, y)).collect();
+    s.join(separator(m))
+}
+
+// Parses a square given by its number or its algebraic name.
+fn parse_square(s: &str) -> Option<(i32, i32)> {
+    if let Ok(n) = s.parse::<usize>() {
+        return from_number(n);
+    }
+    let c: Vec<char> = s.to_lowercase().chars().collect();
+    match (c.len(), c.first().and_then(|&f| FILES.find(f)), c.get(1).and_then(|r| r.to_digit(10))) {
+        (2, Some(x), Some(y)) if y >= 1 => {
+            let (x, y) = (x as i32, y as i32 - 1);
+            square(x, y).map(|_| (x, y))
+        },
+        _ => None
+    }
+}
+
+// Returns the legal move of b given in numeric or algebraic notation. The squares are separated
+// by "-" or "x"; with "x" the move has to be a capture. A move matches if it starts and ends on
+// the first and the last square and visits the squares in between in the given order, so that
+// a multi-jump can be given by its first and last square only, e.g. "22x8". An error is
+// returned if the string is invalid, if no legal move matches or if more than one does.
+pub fn parse_move(b: &Board, s: &str) -> Result<Move, String> {
+    let t = s.trim();
+    let capture = t.contains('x') || t.contains('X');
+    let mut squares = vec![];
+    for p in t.split(&['-', 'x', 'X'][..]) {
+        match parse_square(p) {
+            Some(q) => squares.push(q),
+            None => return Err(format!("invalid move: {}", s))
+        }
+    }
+    if squares.len() < 2 {
+        return Err(format!("invalid move: {}", s));
+    }
+
+    let (first, last) = (squares[0], squares[squares.len() - 1]);
+    let between = &squares[1..squares.len() - 1];
+    let v: Vec<Move> = b.moves().into_iter().filter(|m| {
+        let path = m.path();
+        let mut rest = path[1..path.len() - 1].iter();
+        m.src() == first && m.dst() == last && (!capture || m.is_capture()) &&
+            between.iter().all(|q| rest.any(|p| p == q))
+    }).collect();
+    match v.len() {
+        1 => Ok(v[0]),
+        0 => Err(format!("illegal move: {}", s)),
+        _ => Err(format!("ambiguous move: {}", s))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use board::board::Board;
+    use board::moves::Move;
+    use board::notation::{algebraic, from_number, number, numeric, parse_move};
+
+    #[test]
+    fn squares() {
+        assert_eq!(number(1, 7), Some(1));
+        assert_eq!(number(0, 6), Some(5));
+        assert_eq!(number(6, 0), Some(32));
+        assert_eq!(number(0, 7), None);
+        assert_eq!(from_number(32), Some((6, 0)));
+        assert_eq!(from_number(0), None);
+        assert_eq!(from_number(33), None);
+        for n in 1..33 {
+            let (x, y) = from_number(n).unwrap();
+            assert_eq!(number(x, y), Some(n));
+        }
+    }
+
+    #[test]
+    fn moves() {
+        let b = Board::new();
+        let m = parse_move(&b, "11-15").unwrap();
+        assert_eq!(m, Move::new(&[(5, 5), (4, 4)]).unwrap());
+        assert_eq!(parse_move(&b, "f6-e5").unwrap(), m);
+        assert_eq!(parse_move(&b, " F6-15 ").unwrap(), m);
+        assert_eq!(numeric(&m), "11-15");
+        assert_eq!(algebraic(&m), "f6-e5");
+        for m in b.moves() {
+            assert_eq!(parse_move(&b, &numeric(&m)).unwrap(), m);
+            assert_eq!(parse_move(&b, &algebraic(&m)).unwrap(), m);
+        }
+
+        assert_eq!(parse_move(&b, "11-14").unwrap_err(), "illegal move: 11-14");
+        assert_eq!(parse_move(&b, "11x18").unwrap_err(), "illegal move: 11x18");
+        assert_eq!(parse_move(&b, "11-").unwrap_err(), "invalid move: 11-");
+        assert!(parse_move(&b, "a1-b2").is_err());
+        assert!(parse_move(&b, "e5").is_err());
+        assert!(parse_move(&b, "i1-h2").is_err());
+
+        // A double jump given completely or by its first and last square.
+        let b = Board::from_fen("W:W22:B18,11").unwrap();
+        let m = parse_move(&b, "22x15x8").unwrap();
+        assert_eq!(numeric(&m), "22x15x8");
+        assert_eq!(algebraic(&m), "c3xe5xg7");
+        assert_eq!(parse_move(&b, "22x8").unwrap(), m);
+        assert_eq!(parse_move(&b, "22-15-8").unwrap(), m);
+        assert!(parse_move(&b, "22x15").is_err());
+
+        // A dame which can capture four pieces clockwise or anticlockwise.
+        let b = Board::from_fen("W:WK22:B9,10,17,18").unwrap();
+        assert_eq!(b.moves().len(), 2);
+        assert_eq!(parse_move(&b, "22x22").unwrap_err(), "ambiguous move: 22x22");
+        assert_eq!(numeric(&parse_move(&b, "22x15x6x22").unwrap()), "22x15x6x13x22");
+    }
+}
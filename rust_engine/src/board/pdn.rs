@@ -0,0 +1,393 @@
+use board::board::{Board, GameOutcome, Player};
+use board::history::History;
+use board::moves::Move;
+use board::notation::{numeric, parse_move};
+
+// Games in the Portable Draughts Notation, e.g.
+//
+//   [Event "Example"]
+//   [Black "A"]
+//   [White "B"]
+//   [Result "1-0"]
+//
+//   1. 11-15 23-19 2. 8-11 {a comment} 22-17 3. 15x24 ... 1-0
+//
+// Moves are given in numeric notation, see board::notation. Steps are written with "-", jumps
+// with "x"; a multi-jump may list all squares or only the first and the last one. A position
+// which is not the start position is given by the tag FEN. Results are given from the view of
+// black, the player who moves first: "1-0" black wins, "0-1" white wins, "1/2-1/2" draw and "*"
+// unknown.
+
+pub const RESULTS: [&'static str; 7] = ["1-0", "0-1", "1/2-1/2", "*", "2-0", "0-2", "1-1"];
+
+const LINE_WIDTH: usize = 79;
+
+#[derive(Clone, Debug)]
+pub struct PdnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: Board,
+    pub moves: Vec<Move>,
+    pub comments: Vec<(usize, String)>,     // comments after the given number of moves
+    pub result: String,
+}
+
+// Splits the text of one or more games into tag pairs and the tokens of the moves. Comments
+// are kept as a single token which starts with "{"; variations in parentheses are skipped.
+// Quotes and escapes are only special in the values of tag pairs. A move number is split off
+// the move it is attached to, e.g. "1.11-15".
+fn tokens(s: &str) -> Result<Vec<String>, String> {
+    let mut v = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' | '{' => {
+                let end = if c == '[' { ']' } else { '}' };
+                let mut t = c.to_string();
+                let mut quoted = false;
+                loop {
+                    match chars.next() {
+                        Some('\\') if quoted => t.extend(chars.next()),
+                        Some('"') if c == '[' => { quoted = !quoted; t.push('"'); },
+                        Some(x) if x == end && !quoted => break,
+                        Some(x) => t.push(x),
+                        None => return Err(format!("missing {}", end))
+                    }
+                }
+                v.push(t);
+            },
+            '(' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('(') => depth += 1,
+                        Some(')') => depth -= 1,
+                        Some(_) => {},
+                        None => return Err("missing )".to_string())
+                    }
+                }
+            },
+            c if c.is_whitespace() => {},
+            c => {
+                let mut t = c.to_string();
+                while let Some(&x) = chars.peek() {
+                    if x.is_whitespace() || "[{(".contains(x) {
+                        break;
+                    }
+                    t.push(x);
+                    chars.next();
+                }
+                let m = move_after_number(&t).map(|m| m.to_string());
+                match m {
+                    Some(m) => {
+                        let n = t.len() - m.len();
+                        v.push(t[..n].to_string());
+                        v.push(m);
+                    },
+                    None => v.push(t)
+                }
+            }
+        }
+    }
+    Ok(v)
+}
+
+// Parses a tag pair without the closing bracket, e.g. [Event "Example".
+fn tag(t: &str) -> Result<(String, String), String> {
+    let t = t[1..].trim();
+    match (t.find(char::is_whitespace), t.find('"'), t.rfind('"')) {
+        (Some(i), Some(a), Some(b)) if a > i && b > a => Ok((t[..i].to_string(), t[a + 1..b].to_string())),
+        _ => Err(format!("invalid tag: [{}]", t))
+    }
+}
+
+// Returns the move after a move number without a space, e.g. "11-15" of "1.11-15" or "1...11-15".
+fn move_after_number(t: &str) -> Option<&str> {
+    let m = t.trim_start_matches(|c: char| c.is_ascii_digit());
+    let rest = m.trim_start_matches('.');
+    if m.len() < t.len() && rest.len() < m.len() && !rest.is_empty() {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+// Returns true for move numbers such as "12." or "12...".
+fn is_move_number(t: &str) -> bool {
+    let n = t.trim_end_matches('.');
+    n.len() < t.len() && !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+}
+
+impl PdnGame {
+
+    pub fn new(start: Board) -> PdnGame {
+        PdnGame {
+            tags: vec![],
+            start: start,
+            moves: vec![],
+            comments: vec![],
+            result: "*".to_string(),
+        }
+    }
+
+    // Creates a game from the moves of a history. The result is taken from the board.
+    pub fn from_history(h: &History) -> PdnGame {
+        let mut g = PdnGame::new(h.start());
+        g.moves = h.moves();
+        g.result = match h.board().outcome() {
+            GameOutcome::BlackWins => "1-0",
+            GameOutcome::WhiteWins => "0-1",
+            GameOutcome::Draw(_) => "1/2-1/2",
+            GameOutcome::Ongoing => "*"
+        }.to_string();
+        g
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|t| t.0 == name).map(|t| &t.1[..])
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter().position(|t| t.0 == name) {
+            Some(i) => self.tags[i].1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string()))
+        }
+    }
+
+    // Returns the position after all moves.
+    pub fn board(&self) -> Board {
+        let mut b = self.start;
+        for m in &self.moves {
+            b.make_move(m);
+        }
+        b
+    }
+
+    // Parses a single game.
+    pub fn parse(s: &str) -> Result<PdnGame, String> {
+        let mut v = try!(PdnGame::parse_all(s));
+        match v.len() {
+            1 => Ok(v.remove(0)),
+            0 => Err("no game found".to_string()),
+            _ => Err("more than one game found".to_string())
+        }
+    }
+
+    // Parses all games of a PDN file. Each move is replayed on the board and the first illegal
+    // move is reported with its ply, i.e. the number of the half move starting with 1.
+    pub fn parse_all(s: &str) -> Result<Vec<PdnGame>, String> {
+        let mut games = vec![];
+        let mut g: Option<(PdnGame, Board)> = None;
+        for t in try!(tokens(s)) {
+            if t.starts_with('[') {
+                if let Some((ref x, _)) = g {
+                    if !x.moves.is_empty() || !x.comments.is_empty() {
+                        return Err(format!("tag after the moves: {}]", t));
+                    }
+                }
+                let (name, value) = try!(tag(&t));
+                let mut x = g.take().unwrap_or_else(|| (PdnGame::new(Board::new()), Board::new()));
+                if name == "FEN" {
+                    x.0.start = try!(Board::from_fen(&value));
+                    x.1 = x.0.start;
+                }
+                x.0.set_tag(&name, &value);
+                g = Some(x);
+                continue;
+            }
+
+            let x = g.get_or_insert_with(|| (PdnGame::new(Board::new()), Board::new()));
+            if t.starts_with('{') {
+                x.0.comments.push((x.0.moves.len(), t.trim_start_matches('{').trim().to_string()));
+            } else if RESULTS.contains(&&t[..]) {
+                x.0.result = t;
+                games.push(g.take().unwrap().0);
+            } else if !is_move_number(&t) && !t.starts_with('$') {
+                let ply = x.0.moves.len() + 1;
+                let m = try!(parse_move(&x.1, t.trim_end_matches(|c| c == '!' || c == '?'))
+                    .map_err(|e| format!("{} at ply {}", e, ply)));
+                // replay the single steps or jumps
+                for (x0, y0, x1, y1) in m.hops() {
+                    if let Err(e) = x.1.try_move(x0, y0, x1, y1) {
+                        return Err(format!("illegal move: {} at ply {}: {}", t, ply, e));
+                    }
+                }
+                x.0.moves.push(m);
+            }
+        }
+        // a game without a result
+        if let Some((x, _)) = g {
+            games.push(x);
+        }
+        Ok(games)
+    }
+
+    // Returns the game in PDN. The tags FEN and SetUp are written if the game does not start
+    // from the start position, the tag Result is set to the result of the game.
+    pub fn to_pdn(&self) -> String {
+        let mut tags = self.tags.clone();
+        let mut set = |name: &str, value: &str| {
+            match tags.iter().position(|t| t.0 == name) {
+                Some(i) => tags[i].1 = value.to_string(),
+                None => tags.push((name.to_string(), value.to_string()))
+            }
+        };
+        set("Result", &self.result);
+        if self.start.to_fen() != Board::new().to_fen() || self.start.player() != Player::Black {
+            set("SetUp", "1");
+            set("FEN", &self.start.to_fen());
+        }
+
+        let mut s = String::new();
+        for (name, value) in &tags {
+            s.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        s.push('\n');
+
+        let mut words = vec![];
+        let comments = |n: usize, words: &mut Vec<String>| {
+            for (_, c) in self.comments.iter().filter(|c| c.0 == n) {
+                words.push(format!("{{{}}}", c));
+            }
+        };
+        comments(0, &mut words);
+        let white_first = self.start.player() == Player::White;
+        for (i, m) in self.moves.iter().enumerate() {
+            let ply = i + if white_first { 1 } else { 0 };
+            if ply % 2 == 0 {
+                words.push(format!("{}.", ply / 2 + 1));
+            } else if i == 0 {
+                words.push(format!("{}...", ply / 2 + 1));
+            }
+            words.push(numeric(m));
+            comments(i + 1, &mut words);
+        }
+        words.push(self.result.clone());
+
+        let mut line = String::new();
+        for w in words {
+            if !line.is_empty() && line.len() + 1 + w.len() > LINE_WIDTH {
+                s.push_str(&line);
+                s.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&w);
+        }
+        s.push_str(&line);
+        s.push('\n');
+        s
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use board::board::{Board, GameOutcome, Player};
+    use board::history::History;
+    use board::notation::{numeric, parse_move};
+    use board::pdn::PdnGame;
+
+    const GAME: &'static str = "
+[Event \"Example\"]
+[Black \"A \\\"B\\\" C\"]
+
+1. 11-15 23-19 {the Old Fourteenth
+begins} 2. 8-11 22-17 (2... 21-17) 3. 4-8 17-13 $1 4. 15-18 24-20 5. 9-14 27-23 6. 18x27
+32x23 1/2-1/2
+";
+
+    #[test]
+    fn parse() {
+        let g = PdnGame::parse(GAME).unwrap();
+        assert_eq!(g.tag("Event"), Some("Example"));
+        assert_eq!(g.tag("Black"), Some("A \"B\" C"));
+        assert_eq!(g.moves.len(), 12);
+        assert_eq!(g.comments, vec![(2, "the Old Fourteenth\nbegins".to_string())]);
+        assert_eq!(g.result, "1/2-1/2");
+        let moves: Vec<String> = g.moves.iter().map(numeric).collect();
+        assert_eq!(moves[10], "18x27");
+        assert_eq!(g.board().count_normal(Player::White), 11);
+
+        // written and read again
+        let s = g.to_pdn();
+        assert!(s.contains("[Result \"1/2-1/2\"]"));
+        assert!(s.contains("1. 11-15 23-19 {the Old Fourteenth\nbegins} 2. 8-11 22-17 3. 4-8"));
+        let h = PdnGame::parse(&s).unwrap();
+        assert_eq!(h.moves, g.moves);
+        assert_eq!(h.comments, g.comments);
+        assert_eq!(h.tags, vec![
+            ("Event".to_string(), "Example".to_string()),
+            ("Black".to_string(), "A \"B\" C".to_string()),
+            ("Result".to_string(), "1/2-1/2".to_string())]);
+
+        // two games
+        let v = PdnGame::parse_all(&format!("{}\n{}", GAME, s)).unwrap();
+        assert_eq!(v.len(), 2);
+        assert!(PdnGame::parse(&format!("{}\n{}", GAME, s)).is_err());
+    }
+
+    #[test]
+    fn comments_and_numbers() {
+        // Quotes in comments are not special.
+        let g = PdnGame::parse("1. 11-15 {a 6\" board} 23-19 *").unwrap();
+        assert_eq!(g.moves.len(), 2);
+        assert_eq!(g.comments, vec![(1, "a 6\" board".to_string())]);
+        let g = PdnGame::parse("1. 11-15 {it's \"ok} 23-19 {x\"} 2. 8-11 *").unwrap();
+        assert_eq!(g.moves.len(), 3);
+        assert_eq!(g.comments, vec![(1, "it's \"ok".to_string()), (2, "x\"".to_string())]);
+
+        // Move numbers without a space.
+        let g = PdnGame::parse("1.11-15 23-19 2.8-11 *").unwrap();
+        let moves: Vec<String> = g.moves.iter().map(numeric).collect();
+        assert_eq!(moves, vec!["11-15", "23-19", "8-11"]);
+        let g = PdnGame::parse("[FEN \"W:W27:B24,16\"]\n1...27x20x11 *").unwrap();
+        assert_eq!(numeric(&g.moves[0]), "27x20x11");
+        assert_eq!(PdnGame::parse("1. 11-15 1-0").unwrap().result, "1-0");
+    }
+
+    #[test]
+    fn setup() {
+        // White to move jumps twice.
+        let s = "[FEN \"W:W27:B24,16\"]\n1... 27x20x11 *";
+        let g = PdnGame::parse(s).unwrap();
+        assert_eq!(g.start.player(), Player::White);
+        assert_eq!(g.board().to_fen(), "B:W11:B");
+        assert_eq!(g.board().outcome(), GameOutcome::WhiteWins);
+        assert_eq!(numeric(&g.moves[0]), "27x20x11");
+        let s = g.to_pdn();
+        assert!(s.contains("[FEN \"W:W27:B16,24\"]\n"));
+        assert!(s.contains("[SetUp \"1\"]\n"));
+        assert!(s.ends_with("\n\n1... 27x20x11 *\n"));
+
+        // The squares in between can be left out.
+        assert_eq!(PdnGame::parse("[FEN \"W:W27:B24,16\"] 27x11").unwrap().moves, g.moves);
+        let b = Board::from_fen("W:W27:B24,16").unwrap();
+        assert!(parse_move(&b, "27x19").is_err());
+        assert!(parse_move(&b, "27-20-11").is_ok());
+    }
+
+    #[test]
+    fn illegal() {
+        let e = PdnGame::parse("1. 11-15 23-19 2. 15-18 19-15 *").unwrap_err();
+        assert_eq!(e, "illegal move: 19-15 at ply 4");
+        let e = PdnGame::parse("1. 11-15 23-19 2. 8-11 22-18 3. 10-14 18-23 *").unwrap_err();
+        assert_eq!(e, "illegal move: 10-14 at ply 5");
+        assert!(PdnGame::parse("1. 11-15 {no end").is_err());
+        assert!(PdnGame::parse("1. 11-15 {\"} 23-19 *").is_ok());
+        assert!(PdnGame::parse("1. 11-15 [Event \"late\"]").is_err());
+        assert!(PdnGame::parse("1. 33-15").is_err());
+    }
+
+    #[test]
+    fn history() {
+        let mut h = History::new(Board::new());
+        for m in &["11-15", "23-19", "8-11"] {
+            let m = parse_move(h.board(), m).unwrap();
+            h.make(&m);
+        }
+        let g = PdnGame::from_history(&h);
+        assert_eq!(g.to_pdn(), "[Result \"*\"]\n\n1. 11-15 23-19 2. 8-11 *\n");
+    }
+}
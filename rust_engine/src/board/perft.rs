@@ -0,0 +1,88 @@
+use board::board::Board;
+use board::moves::Move;
+
+// Performance test of the move generator: counts the positions which are reached after depth
+// complete moves, i.e. multi-jumps count as one move. The numbers can be compared with the
+// published ones, e.g. 7, 49, 302, 1469, 7361, 36768, 179740, 845931, ... from the start
+// position. The draw rules depend on the history of a game and are not applied, so a position
+// is only a leaf if the depth has been reached or if the player to move cannot move.
+
+// Returns the number of positions after depth moves.
+pub fn perft(b: &Board, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let v = b.moves();
+    if depth == 1 {
+        return v.len() as u64;
+    }
+    v.iter().map(|m| perft(&play(b, m), depth - 1)).sum()
+}
+
+// Returns the number of positions after depth moves for each move of the player to move.
+pub fn divide(b: &Board, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return vec![];
+    }
+    b.moves().into_iter().map(|m| (m, perft(&play(b, &m), depth - 1))).collect()
+}
+
+// Returns the board after move m. The count of the move rule is reset first.
+fn play(b: &Board, m: &Move) -> Board {
+    let mut c = *b;
+    c.rehash();
+    assert!(c.make_move(m), "the move generator returned an invalid move");
+    c
+}
+
+
+#[cfg(test)]
+mod tests {
+    use board::board::Board;
+    use board::notation::numeric;
+    use board::perft::{divide, perft};
+
+    #[test]
+    fn start() {
+        // Published numbers of American checkers, see e.g. the perft results of Aart Bik.
+        let b = Board::new();
+        let n: Vec<u64> = (0..9).map(|d| perft(&b, d)).collect();
+        assert_eq!(n, vec![1, 7, 49, 302, 1469, 7361, 36768, 179740, 845931]);
+
+        let d = divide(&b, 3);
+        assert_eq!(d.len(), 7);
+        assert_eq!(d.iter().map(|&(_, n)| n).sum::<u64>(), 302);
+        let m: Vec<String> = d.iter().map(|e| numeric(&e.0)).collect();
+        for s in &["9-13", "9-14", "10-14", "10-15", "11-15", "11-16", "12-16"] {
+            assert!(m.contains(&s.to_string()));
+        }
+        assert!(divide(&b, 0).is_empty());
+    }
+
+    #[test]
+    fn captures() {
+        // The numbers of these positions have been counted by hand.
+
+        // A man which has to choose between a triple jump to the crowning row and a double jump.
+        let b = Board::from_fen("W:W27:B23,14,15,6").unwrap();
+        let m: Vec<String> = b.moves().iter().map(numeric).collect();
+        assert_eq!(m.len(), 2);
+        assert!(m.contains(&"27x18x9x2".to_string()));
+        assert!(m.contains(&"27x18x11".to_string()));
+
+        // A dame which captures four men clockwise or anticlockwise and ends where it started.
+        let b = Board::from_fen("W:WK22:B9,10,17,18").unwrap();
+        assert_eq!(perft(&b, 1), 2);
+        assert_eq!(perft(&b, 2), 0);
+
+        // A man which is crowned by a jump ends the move, although the new dame could capture.
+        let b = Board::from_fen("W:W9:B6,7").unwrap();
+        assert_eq!(b.moves().iter().map(numeric).collect::<Vec<String>>(), vec!["9x2"]);
+        assert_eq!(perft(&b, 2), 2);
+
+        // Black cannot move.
+        let b = Board::from_fen("B:W25,30:B21").unwrap();
+        assert_eq!(perft(&b, 1), 0);
+        assert_eq!(perft(&b, 3), 0);
+    }
+}
@@ -0,0 +1,164 @@
+use rustc_serialize::{Decodable, Decoder};
+
+use board::board::{Board, Color, Player, square};
+
+// Sets up arbitrary positions, e.g. for an analysis board: the pieces, the player to move, a
+// piece which has to continue a multi-jump and the move number. The position is only checked
+// by build so that it can be inconsistent while it is edited.
+//
+//   let mut p = Position::new();
+//   p.put(2, 2, Color::WhiteNormal);
+//   p.put(3, 3, Color::BlackNormal);
+//   p.set_player(Player::White);
+//   let b = p.build();
+#[derive(Clone, Debug)]
+pub struct Position {
+    squares: Vec<Color>,                // indexed by y * 8 + x
+    next_move: Player,
+    jumping: Option<(i32, i32)>,
+    move_no: i32,
+}
+
+impl Position {
+    // Creates an empty board with black to move.
+    pub fn new() -> Position {
+        Position {
+            squares: vec![Color::Empty; 64],
+            next_move: Player::Black,
+            jumping: None,
+            move_no: 0,
+        }
+    }
+
+    // Creates a position from the pieces, the player to move and the move number of board b.
+    pub fn from_board(b: &Board) -> Position {
+        let mut p = Position::new();
+        for i in 0..64 {
+            p.squares[i] = b.color(i as i32 % 8, i as i32 / 8).unwrap();
+        }
+        p.next_move = b.player();
+        p.move_no = b.move_no();
+        p
+    }
+
+    // Puts the piece c on (x, y); Color::Empty removes a piece. Fails on a light square.
+    pub fn put(&mut self, x: i32, y: i32, c: Color) -> Result<(), String> {
+        match square(x, y) {
+            Some(_) => {
+                self.squares[(y * 8 + x) as usize] = c;
+                Ok(())
+            },
+            None => Err(format!("({}, {}) is not a dark square", x, y))
+        }
+    }
+
+    // Removes all pieces.
+    pub fn clear(&mut self) {
+        self.squares = vec![Color::Empty; 64];
+        self.jumping = None;
+    }
+
+    pub fn set_player(&mut self, p: Player) {
+        self.next_move = p;
+    }
+
+    // Sets the piece which has to continue a multi-jump or None at the start of a turn.
+    pub fn set_jumping(&mut self, j: Option<(i32, i32)>) {
+        self.jumping = j;
+    }
+
+    pub fn set_move_no(&mut self, n: i32) {
+        self.move_no = n;
+    }
+
+    // Returns the board of the position. A player who cannot move has lost. All problems of an
+    // invalid position are returned, see Board::validate.
+    pub fn build(&self) -> Result<Board, Vec<String>> {
+        let mut b = try!(Board::rebuild(&self.squares, self.next_move));
+        if let Some((x, y)) = self.jumping {
+            try!(b.set_jumping(x, y).map_err(|e| vec![e]));
+        }
+        b.set_move_no(self.move_no);
+        Ok(b)
+    }
+}
+
+// A position is read from JSON with the keys of a board, see Board, of which only "board" is
+// required, e.g. {"board": [...], "next_move": "White", "jumping": [2, 2], "move_no": 10}.
+impl Decodable for Position {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Position, D::Error> {
+        d.read_struct("Position", 4, |d| {
+            let v: Vec<Color> = try!(d.read_struct_field("board", 0, Decodable::decode));
+            let next_move: Option<Player> = try!(d.read_struct_field("next_move", 1, Decodable::decode));
+            let jumping: Option<(i32, i32)> = try!(d.read_struct_field("jumping", 2, Decodable::decode));
+            let move_no: Option<i32> = try!(d.read_struct_field("move_no", 3, Decodable::decode));
+            if v.len() != 64 {
+                return Err(d.error("the board must have 64 squares"));
+            }
+            Ok(Position {
+                squares: v,
+                next_move: next_move.unwrap_or(Player::Black),
+                jumping: jumping,
+                move_no: move_no.unwrap_or(0),
+            })
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json;
+    use board::board::{Board, Color, GameOutcome, Player};
+    use board::position::Position;
+
+    #[test]
+    fn build() {
+        let mut p = Position::new();
+        assert_eq!(p.build().unwrap().outcome(), GameOutcome::WhiteWins);
+        p.put(2, 2, Color::WhiteNormal).unwrap();
+        p.put(3, 3, Color::BlackNormal).unwrap();
+        assert!(p.put(3, 2, Color::BlackNormal).is_err());
+        p.set_player(Player::White);
+        p.set_move_no(7);
+        let b = p.build().unwrap();
+        assert_eq!(b.to_fen(), Board::from_fen("W:W22:B18").unwrap().to_fen());
+        assert_eq!(b.move_no(), 7);
+        assert_eq!(b.valid_moves(), vec![(2, 2, 4, 4)]);
+
+        let b = Board::from_fen("B:W21,K30:B1,2").unwrap();
+        assert_eq!(Position::from_board(&b).build().unwrap().to_json(), b.to_json());
+
+        p.clear();
+        p.put(0, 0, Color::WhiteNormal).unwrap();
+        p.put(1, 7, Color::WhiteNormal).unwrap();
+        assert!(p.build().is_err());
+    }
+
+    #[test]
+    fn jumping() {
+        // White has jumped from 22 to 15 and has to continue over 11.
+        let mut p = Position::new();
+        p.put(4, 4, Color::WhiteNormal).unwrap();
+        p.put(5, 5, Color::BlackNormal).unwrap();
+        p.put(1, 5, Color::BlackNormal).unwrap();
+        p.set_player(Player::White);
+        p.set_jumping(Some((4, 4)));
+        let b = p.build().unwrap();
+        assert_eq!(b.movable_pieces(), vec![(4, 4)]);
+        assert_eq!(b.valid_moves(), vec![(4, 4, 6, 6)]);
+        let d: Board = json::decode(&b.to_json()).unwrap();
+        assert_eq!(d.hash(), b.hash());
+
+        p.set_jumping(Some((1, 5)));
+        assert_eq!(p.build().unwrap_err(), vec!["the piece on (1, 5) cannot continue a jump"]);
+        p.set_jumping(Some((0, 0)));
+        assert!(p.build().is_err());
+
+        let p: Position = json::decode(&format!(
+            "{{\"board\": {}, \"next_move\": \"White\", \"jumping\": [4, 4], \"move_no\": 3}}",
+            json::encode(&(0..64).map(|i| b.color(i % 8, i / 8).unwrap()).collect::<Vec<Color>>()).unwrap())).unwrap();
+        assert_eq!(p.build().unwrap().to_json(), b.to_json().replace("\"move_no\":0", "\"move_no\":3"));
+        assert!(json::decode::<Position>("{\"board\": []}").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std;
+
+// The rules of a variant of draughts. Board follows the rules given by a RuleSet, by default
+// AmericanRules, see Board::set_rules. A RuleSet only contains the rules which Board can
+// change: Board always plays on 8 x 8 squares with kings which move one square at a time, so
+// that e.g. a variant in which men capture backwards is a new RuleSet and not a change of
+// Board.
+
+// What happens if a man reaches the row on which it is crowned during a capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapturePromotion {
+    EndsCapture,        // the man is crowned and the move ends, e.g. American checkers
+    ContinuesAsKing,    // the man is crowned and continues to capture as a king
+    AtEndOfMove,        // the man continues to capture as a man and is only crowned if the move ends on the row
+}
+
+pub trait RuleSet: std::fmt::Debug + Sync {
+    // The name of the rules, e.g. in the JSON of a board.
+    fn name(&self) -> &'static str;
+
+    // Returns true if men capture backwards as well as forwards.
+    fn men_capture_backwards(&self) -> bool;
+
+    // Returns true if the move which captures the most pieces has to be made.
+    fn capture_majority(&self) -> bool;
+
+    fn capture_promotion(&self) -> CapturePromotion;
+}
+
+// The rules of American checkers and English draughts.
+#[derive(Clone, Copy, Debug)]
+pub struct AmericanRules;
+
+impl RuleSet for AmericanRules {
+    fn name(&self) -> &'static str {
+        "american"
+    }
+
+    fn men_capture_backwards(&self) -> bool {
+        false
+    }
+
+    fn capture_majority(&self) -> bool {
+        false
+    }
+
+    fn capture_promotion(&self) -> CapturePromotion {
+        CapturePromotion::EndsCapture
+    }
+}
+
+pub static AMERICAN: AmericanRules = AmericanRules;
+
+// Returns the rules with the given name.
+pub fn by_name(name: &str) -> Option<&'static RuleSet> {
+    match name {
+        "american" => Some(&AMERICAN),
+        _ => None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json;
+    use board::board::{Board, Color};
+    use board::notation::numeric;
+    use board::rules::{CapturePromotion, RuleSet, AMERICAN};
+
+    // Rules which are defined outside of Board.
+    #[derive(Debug)]
+    struct Variant {
+        backwards: bool,
+        majority: bool,
+        promotion: CapturePromotion,
+    }
+
+    impl RuleSet for Variant {
+        fn name(&self) -> &'static str { "variant" }
+        fn men_capture_backwards(&self) -> bool { self.backwards }
+        fn capture_majority(&self) -> bool { self.majority }
+        fn capture_promotion(&self) -> CapturePromotion { self.promotion }
+    }
+
+    static BACKWARDS: Variant = Variant { backwards: true, majority: false, promotion: CapturePromotion::EndsCapture };
+    static MAJORITY: Variant = Variant { backwards: false, majority: true, promotion: CapturePromotion::EndsCapture };
+    static AS_KING: Variant = Variant { backwards: false, majority: false, promotion: CapturePromotion::ContinuesAsKing };
+    static AT_END: Variant = Variant { backwards: true, majority: true, promotion: CapturePromotion::AtEndOfMove };
+
+    fn board(fen: &str, r: &'static RuleSet) -> Board {
+        let mut b = Board::from_fen(fen).unwrap();
+        b.set_rules(r);
+        b
+    }
+
+    fn moves(b: &Board) -> Vec<String> {
+        let mut v: Vec<String> = b.moves().iter().map(numeric).collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn american() {
+        let b = Board::new();
+        assert_eq!(b.rules().name(), "american");
+        assert_eq!(moves(&board("W:W18:B22", &AMERICAN)), vec!["18-14", "18-15"]);
+        assert_eq!(moves(&board("W:W21,22:B11,17,18", &AMERICAN)), vec!["21x14", "22x13", "22x15x8"]);
+        assert_eq!(moves(&board("W:W9:B6,7", &AMERICAN)), vec!["9x2"]);
+    }
+
+    #[test]
+    fn variants() {
+        // A man captures backwards.
+        assert_eq!(moves(&board("W:W18:B22", &BACKWARDS)), vec!["18x25"]);
+
+        // The capture of two pieces has to be made.
+        let mut b = board("W:W21,22:B11,17,18", &MAJORITY);
+        assert_eq!(moves(&b), vec!["22x15x8"]);
+        assert_eq!(b.movable_pieces(), vec![(2, 2)]);
+        assert_eq!(b.valid_moves(), vec![(2, 2, 4, 4)]);
+        assert!(b.try_move(0, 2, 2, 4).is_err());
+        b.move_it(2, 2, 4, 4);
+        assert_eq!(b.valid_moves(), vec![(4, 4, 6, 6)]);
+
+        // A man which is crowned continues to capture as a dame.
+        let mut b = board("W:W9:B6,7", &AS_KING);
+        assert_eq!(moves(&b), vec!["9x2x11"]);
+        assert!(b.try_move(1, 5, 3, 7).unwrap().promoted);
+        assert_eq!(b.color(3, 7), Some(Color::WhiteDame));
+        assert!(b.validate().is_ok());
+
+        // A man which passes the row on which it is crowned stays a man.
+        let mut b = board("W:W9:B6,7", &AT_END);
+        assert_eq!(moves(&b), vec!["9x2x11"]);
+        let r = b.try_move(1, 5, 3, 7).unwrap();
+        assert!(!r.promoted && !r.turn_over);
+        assert_eq!(b.color(3, 7), Some(Color::WhiteNormal));
+        assert!(b.validate().is_ok());
+        b.move_it(3, 7, 5, 5);
+        assert_eq!(b.color(5, 5), Some(Color::WhiteNormal));
+
+        // The same man is crowned if the move ends on the row.
+        let mut b = board("W:W9:B6", &AT_END);
+        assert!(b.try_move(1, 5, 3, 7).unwrap().promoted);
+        assert_eq!(b.color(3, 7), Some(Color::WhiteDame));
+    }
+
+    #[test]
+    fn json() {
+        // Only known rules can be decoded.
+        let j = board("W:W18:B22", &BACKWARDS).to_json();
+        assert!(j.contains("\"rules\":\"variant\""));
+        assert!(json::decode::<Board>(&j).is_err());
+        let d: Board = json::decode(&j.replace("\"variant\"", "\"american\"")).unwrap();
+        assert_eq!(d.rules().name(), "american");
+    }
+}
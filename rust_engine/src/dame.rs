@@ -1,53 +1,30 @@
-use std;
-
 use ai::minimax;
 use board::board;
-use board::board::Board;
+use board::board::{Board, Undo};
+use board::history::Positions;
+use board::moves::Move;
 use ai::minimax::Game;
+use eval::{Evaluator, Term, WeightedEvaluator, Weights};
 
-#[derive(Debug, Clone, Copy)]
-pub struct DameMove {
-    pub src_x: i32,
-    pub src_y: i32,
-    pub dst_x: i32,
-    pub dst_y: i32
-}
-
-impl DameMove {
-    pub fn new(x: (i32, i32, i32, i32)) -> DameMove {
-        DameMove {
-            src_x: x.0,
-            src_y: x.1,
-            dst_x: x.2,
-            dst_y: x.3
-        }
-    }
-}
-
-impl std::fmt::Display for DameMove {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let v = vec!["a", "b", "c", "d", "e", "f", "g", "h"];
-        write!(f, "{}{}-{}{}", v[self.src_x as usize], self.src_y + 1, v[self.dst_x as usize], self.dst_y + 1)
-    }
-}
+// A move of the search is a complete move including all jumps of a multi-jump.
+pub type DameMove = Move;
 
 #[derive(Debug, Clone)]
-pub struct Dame {
+pub struct Dame<E = WeightedEvaluator> {
     pub b: Board,
-    pub ai: board::Player
+    pub ai: board::Player,
+    pub eval: E,
+    pub positions: Positions,   // earlier positions of the game for the detection of repetitions
+    undo: Vec<Undo>
 }
 
 impl Dame {
 
     pub fn new(b: Board) -> Dame {
-        let p = b.player();
-        Dame {
-            b: b,
-            ai: p,
-        }
+        Dame::with_evaluator(b, WeightedEvaluator::new(Weights::positional()))
     }
 
-    fn avg_distance(a: Vec<(i32, i32)>, b: Vec<(i32, i32)>) -> f64 {
+    pub fn avg_distance(a: Vec<(i32, i32)>, b: Vec<(i32, i32)>) -> f64 {
 
         let mut d = 0.0;
         if a.len() > 0 && b.len() > 0 {
@@ -60,7 +37,38 @@ impl Dame {
     }
 }
 
-impl Game<DameMove> for Dame {
+impl<E: Evaluator + Clone> Dame<E> {
+
+    pub fn with_evaluator(b: Board, e: E) -> Dame<E> {
+        let p = b.player();
+        Dame {
+            b: b,
+            ai: p,
+            eval: e,
+            positions: Positions::new(&b),
+            undo: vec![]
+        }
+    }
+
+    // Returns the terms of the evaluation of the position from the view of the AI.
+    pub fn breakdown(&self) -> Vec<Term> {
+        self.eval.terms(&self.b, self.ai)
+    }
+
+    // Returns the terms of the evaluation of the position after the moves of path, e.g. of the
+    // leaf at the end of MiniMax::path().
+    pub fn leaf_breakdown(&self, path: &[DameMove]) -> Vec<Term> {
+        let mut b = self.b;
+        let mut p = self.positions.clone();
+        for m in path {
+            b.make_move(m);
+            p.push(&mut b);
+        }
+        self.eval.terms(&b, self.ai)
+    }
+}
+
+impl<E: Evaluator + Clone> Game<DameMove> for Dame<E> {
 
     fn current_player(&self) -> minimax::Player {
         match self.ai == self.b.player() {
@@ -70,63 +78,81 @@ impl Game<DameMove> for Dame {
     }
 
     fn valid_moves(&self) -> Vec<DameMove> {
-        self.b.valid_moves().iter().map(|&x| DameMove::new(x)).collect()
+        self.b.moves()
     }
 
     fn finished(&self) -> bool {
         self.b.finished()
     }
 
-    // returns large values (e.g. +1) if AI has an advantage
-    // returns small values (e.g. -1) if HUMAN has an advantage
-    fn score(&self) -> f64 {
-
-        // check for win/loose
-        let mut s0: f64 = 0.0;
-        if self.b.winner() == self.ai { // AI wins
-            s0 = 1.0;
-        }
-        if self.b.finished() && self.b.winner() != self.ai { // HUMAN wins
-            s0 = -1.0;
-        }
-
-        // count number of pieces; bzw. the advantage
-        let n_ai = self.b.count_normal(self.ai);
-        let n_hm = self.b.count_normal(self.b.other_player(self.ai));
-        let s1: f64 = (n_ai - n_hm) as f64 / 12.0;
-
-        // advantage in Damen
-        let d_ai = self.b.count_dame(self.ai);
-        let d_hm = self.b.count_dame(self.b.other_player(self.ai));
-        let s2: f64 = (d_ai - d_hm) as f64 / 12.0;
+    // The hash does not cover the positions, so a score of the transposition table may depend on
+    // a repetition of another path to the same position.
+    fn hash(&self) -> u64 {
+        self.b.hash()
+    }
 
-        let s3: f64 = d_ai as f64;
+    fn capture_pending(&self) -> bool {
+        self.b.must_capture()
+    }
 
-        // if AI has more pieces play more aggressive
-//        let a = self.b.positions(self.ai);
-//        let b = self.b.positions(self.b.other_player(self.ai));
-//        let d = Dame::avg_distance(a, b);
-        //println!("d = {}", d);
+    fn is_capture(&self, m: DameMove) -> bool {
+        m.is_capture()
+    }
 
-        let r = s0 * 20.0 + s1 * 1.0 + s2 * 3.0 + s3; // + d * 0.1;
+    fn is_promotion(&self, m: DameMove) -> bool {
+        let ((x, y), (_, dy)) = (m.src(), m.dst());
+        self.b.is_promotion(x, y, dy)
+    }
 
-        r
+    // returns large values (e.g. +1) if AI has an advantage
+    // returns small values (e.g. -1) if HUMAN has an advantage
+    fn score(&self) -> f64 {
+        self.eval.score(&self.b, self.ai)
     }
 
-    fn set(&self, m: DameMove) -> Dame {
-        let mut b = self.b.clone();
-        b.move_it(m.src_x, m.src_y, m.dst_x, m.dst_y);
+    fn set(&self, m: DameMove) -> Dame<E> {
+        let mut b = self.b;
+        b.make_move(&m);
+        let mut p = self.positions.clone();
+        p.push(&mut b);
         Dame {
             b: b,
-            ai: self.ai
+            ai: self.ai,
+            eval: self.eval.clone(),
+            positions: p,
+            undo: vec![]
         }
     }
+
+    fn make(&mut self, m: DameMove) {
+        match self.b.make(&m) {
+            Some(u) => {
+                self.positions.push(&mut self.b);
+                self.undo.push(u)
+            },
+            _ => panic!("invalid move {}", m)
+        }
+    }
+
+    fn unmake(&mut self) {
+        let u = self.undo.pop().expect("no move to take back");
+        self.b.unmake(u);
+        self.positions.pop();
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use Dame;
+    use ai;
+    use ai::minimax::{Game, MiniMax};
+    use board::board::{Board, Player};
+    use eval::{WeightedEvaluator, Weights};
+
+    fn sum(d: &Dame, path: &[::DameMove]) -> f64 {
+        d.leaf_breakdown(path).iter().map(|t| t.contribution).sum()
+    }
 
     #[test]
     fn dame_avg_distance() {
@@ -142,4 +168,62 @@ mod tests {
         b = vec![(2, 2), (3, 4)];
         assert!((Dame::avg_distance(a, b) - 3.212).abs() < 0.001);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn breakdown() {
+        let mut b = Board::new();
+        b.move_it(1, 5, 0, 4);
+        b.move_it(2, 2, 3, 3);
+        let d = Dame::new(b);
+        let t = d.breakdown();
+        assert_eq!(t.len(), 13);
+        assert!(t.iter().any(|t| t.name == "center" && t.raw == -1.0));
+        assert!((sum(&d, &[]) - d.score()).abs() < 1e-9);
+
+        let mut x = MiniMax::new(3);
+        x.minimax(d.clone());
+        assert_eq!(x.path().len(), 4);
+        assert!((sum(&d, &x.path()) - x.score()).abs() < 1e-9);
+
+        // The path found with the transposition table ends at the leaf which has been scored.
+        for b in vec![b, Board::from_fen("B:WK22,K24:BK6,K8").unwrap()] {
+            let d = Dame::new(b);
+            for &depth in [4, 5, 6].iter() {
+                let mut x = MiniMax::new(depth);
+                x.set_alpha_beta(true);
+                x.set_quiescence(true);
+                x.set_move_ordering(true);
+                x.set_table_size(1);
+                x.minimax(d.clone());
+                assert!(x.table_hits() > 0);
+                assert!(x.path().len() > depth);
+                assert!((sum(&d, &x.path()) - x.score()).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn symmetry() {
+        // The score of the AI is the same on the flipped position if the AI plays the other
+        // color. From the view of a fixed color the scores are opposite, except for the term
+        // own_dames, which counts the dames of one color only.
+        let no_own_dames = Weights { own_dames: 0.0, .. Weights::positional() };
+        for _ in 0..20 {
+            let mut b = Board::new();
+            while ai::random(&mut b) {
+                let f = b.flip_colors();
+                assert!((Dame::new(b).score() - Dame::new(f).score()).abs() < 1e-9);
+                let t: Vec<f64> = Dame::new(b).breakdown().iter().map(|t| t.raw).collect();
+                let u: Vec<f64> = Dame::new(f).breakdown().iter().map(|t| t.raw).collect();
+                assert_eq!(t.len(), u.len());
+                assert!(t.iter().zip(u.iter()).all(|(x, y)| (x - y).abs() < 1e-9));
+
+                let mut d = Dame::with_evaluator(b, WeightedEvaluator::new(no_own_dames));
+                let mut e = Dame::with_evaluator(f, WeightedEvaluator::new(no_own_dames));
+                d.ai = Player::White;
+                e.ai = Player::White;
+                assert!((d.score() + e.score()).abs() < 1e-9);
+            }
+        }
+    }
+}
@@ -6,21 +6,48 @@ extern crate time;
 extern crate futures;
 extern crate futures_cpupool;
 
+use std::sync::Mutex;
 use rustc_serialize::json;
-use cpython::{Python, PyResult};
+use cpython::{Python, PyErr, PyResult, exc};
 
 pub mod board;
 pub mod ai;
 pub mod dame;
+pub mod eval;
 
-use ai::minimax::MiniMax;
-use board::board::Board;
-use dame::Dame;
+use ai::minimax::{Game, MiniMax};
+use ai::ponder::Ponder;
+use board::board::{Board, Player};
+use board::history::Positions;
+use board::notation::parse_move;
+use board::position::Position;
+use dame::{Dame, DameMove};
+use eval::Term;
 
-fn decode_board(b: String) -> Board {
-    let mut b: Board = json::decode(&b).unwrap();
-    b.clear_last_moves();
-    b
+// Decodes a board and raises ValueError if the JSON is not a valid board.
+fn decode_board(py: Python, b: String) -> PyResult<Board> {
+    match json::decode::<Board>(&b) {
+        Ok(mut b) => {
+            b.clear_last_moves();
+            Ok(b)
+        },
+        Err(e) => Err(PyErr::new::<exc::ValueError, _>(py, format!("invalid board: {}", e)))
+    }
+}
+
+// Decodes a board together with the earlier positions of the game, see Positions::to_json.
+fn decode_game(py: Python, s: String) -> PyResult<(Board, Positions)> {
+    let b = try!(decode_board(py, s.clone()));
+    match Positions::from_json(&s, &b) {
+        Ok(p) => Ok((b, p)),
+        Err(e) => Err(PyErr::new::<exc::ValueError, _>(py, format!("invalid board: {}", e)))
+    }
+}
+
+fn dame(b: Board, p: Positions) -> Dame {
+    let mut d = Dame::new(b);
+    d.positions = p;
+    d
 }
 
 // exported python functions
@@ -29,49 +56,259 @@ fn new_game(_: Python) -> PyResult<String> {
     Ok(Board::new().to_json())
 }
 
-fn moves_for(_: Python, b: String, x: i32, y: i32) -> PyResult<Vec<(i32, i32)>> {
-    let b = decode_board(b);
+// Returns the position given in FEN, e.g. "B:W21-32:B1-12", as JSON.
+fn from_fen(py: Python, fen: String) -> PyResult<String> {
+    match Board::from_fen(&fen) {
+        Ok(b) => Ok(b.to_json()),
+        Err(e) => Err(PyErr::new::<exc::ValueError, _>(py, e))
+    }
+}
+
+fn to_fen(py: Python, b: String) -> PyResult<String> {
+    Ok(try!(decode_board(py, b)).to_fen())
+}
+
+fn moves_for(py: Python, b: String, x: i32, y: i32) -> PyResult<Vec<(i32, i32)>> {
+    let b = try!(decode_board(py, b));
     match b.mv(x, y) {
         Some(v) => Ok(v.iter().map(|ref p| (p.x, p.y)).collect()),
         _ => Ok(vec![])
     }
 }
 
-fn move_it(_: Python, b: String, x: i32, y: i32, dx: i32, dy: i32) -> PyResult<String> {
-    let mut b = decode_board(b);
-    b.move_it(x, y, dx, dy);
-    Ok(b.to_json())
+// Makes a single step or jump. Raises ValueError with the reason if the move is not valid.
+fn move_it(py: Python, b: String, x: i32, y: i32, dx: i32, dy: i32) -> PyResult<String> {
+    let (mut b, mut p) = try!(decode_game(py, b));
+    match b.try_move(x, y, dx, dy) {
+        Ok(r) => {
+            if r.turn_over {
+                p.push(&mut b);
+            }
+            Ok(p.to_json(&b))
+        },
+        Err(e) => Err(PyErr::new::<exc::ValueError, _>(py, format!("{}", e)))
+    }
+}
+
+// Sets up a position for an analysis board. The position is given as JSON with the squares and
+// optionally the player to move, the piece which continues a jump and the move number, e.g.
+// {"board": [...], "next_move": "White", "jumping": [2, 2], "move_no": 10}. Raises ValueError
+// with all problems found if the position is not valid.
+fn setup_position(py: Python, p: String) -> PyResult<String> {
+    let p = try!(json::decode::<Position>(&p)
+        .map_err(|e| PyErr::new::<exc::ValueError, _>(py, format!("invalid position: {}", e))));
+    match p.build() {
+        Ok(b) => Ok(b.to_json()),
+        Err(e) => Err(PyErr::new::<exc::ValueError, _>(py, format!("invalid position: {}", e.join("; "))))
+    }
 }
 
-fn ai_random(_: Python, b: String) -> PyResult<String> {
-    let mut b = decode_board(b);
-    ai::random(&mut b);
-    Ok(b.to_json())
+// Makes a move given in numeric or algebraic notation, e.g. "11-15" or "c3xe5".
+fn play_move(py: Python, b: String, m: String) -> PyResult<String> {
+    let (mut b, mut p) = try!(decode_game(py, b));
+    match parse_move(&b, &m) {
+        Ok(m) => {
+            b.make_move(&m);
+            p.push(&mut b);
+            Ok(p.to_json(&b))
+        },
+        Err(e) => Err(PyErr::new::<exc::ValueError, _>(py, e))
+    }
+}
+
+// Makes a random move; a finished game is returned unchanged.
+fn ai_random(py: Python, b: String) -> PyResult<String> {
+    let (mut b, mut p) = try!(decode_game(py, b));
+    let player = b.player();
+    if ai::random(&mut b) && (b.player() != player || b.finished()) {
+        p.push(&mut b);
+    }
+    Ok(p.to_json(&b))
+}
+
+// Memory in MB for the transposition table of each search.
+const TABLE_SIZE: usize = 16;
+
+fn configure(x: &mut MiniMax<DameMove>, threads: usize) {
+    x.set_alpha_beta(true);
+    x.set_threads(threads);
+    x.set_quiescence(true);
+    x.set_move_ordering(true);
+    x.set_table_size(TABLE_SIZE);
 }
 
-fn ai_minimax(_: Python, b: String, depth: usize) -> PyResult<String> {
-    let mut d = Dame::new(decode_board(b));
+// Lets the AI move until the other player is to move; a finished game is returned unchanged.
+fn ai_play<F>((b, p): (Board, Positions), search: F) -> String
+    where F: Fn() -> MiniMax<DameMove>
+{
+    let mut d = dame(b, p);
 
     while !d.b.finished() && d.b.player() == d.ai {
-        let mut x = MiniMax::new(depth);
-        let m = x.minimax(d.clone());
-        println!("configured depth: {}", depth);
+        let mut x = search();
+        let m = match x.minimax(d.clone()) {
+            Some(m) => m,
+            None => break
+        };
+        println!("searched depth  : {}", x.depth());
         println!("scores computed : {}", x.scores());
+        println!("cutoffs         : {}", x.cutoffs());
+        println!("first move cuts : {} / {}", x.first_move_cutoffs(), x.cutoff_nodes());
+        println!("table hits      : {} / {}", x.table_hits(), x.table_hits() + x.table_misses());
         println!("winning score   : {}", x.score());
         println!("time in ms      : {}", x.duration_ms());
         println!("path            : {}", x.path().iter().map(|&x| format!("{}", x)).collect::<Vec<_>>().join(", "));
-        d.b.move_it(m.src_x, m.src_y, m.dst_x, m.dst_y);
+        d = d.set(m);
     }
-    Ok(d.b.to_json())
+    d.positions.to_json(&d.b)
+}
+
+fn ai_minimax(py: Python, b: String, depth: usize, threads: usize) -> PyResult<String> {
+    Ok(ai_play(try!(decode_game(py, b)), || {
+        let mut x = MiniMax::new(depth);
+        configure(&mut x, threads);
+        x
+    }))
+}
+
+fn ai_minimax_time(py: Python, b: String, ms: i64, threads: usize) -> PyResult<String> {
+    Ok(ai_play(try!(decode_game(py, b)), || {
+        let mut x = MiniMax::with_time_budget(ms);
+        configure(&mut x, threads);
+        x
+    }))
+}
+
+// Time in ms after which a search on the human's time stops if the human has not moved.
+const PONDER_MS: i64 = 60 * 1000;
+
+// The searches on the human's time by the id of the game and the color of the AI. Only the
+// game in which the AI moved last ponders, so that abandoned games do not keep searches.
+static PONDERING: Mutex<Vec<(String, Player, Ponder<DameMove>)>> = Mutex::new(Vec::new());
+
+fn take_ponder(uid: &str) -> Option<(Player, Ponder<DameMove>)> {
+    let mut v = PONDERING.lock().unwrap();
+    v.iter().position(|e| e.0 == uid).map(|i| {
+        let (_, ai, p) = v.remove(i);
+        (ai, p)
+    })
+}
+
+fn timed_search(ms: i64, threads: usize) -> MiniMax<DameMove> {
+    let mut x = MiniMax::with_time_budget(ms);
+    configure(&mut x, threads);
+    x
+}
+
+// Lets the AI of the game uid move like ai_minimax_time and then searches the position after
+// the predicted reply until the human's move arrives, see ponder_move. If the human has played
+// the predicted reply, that search is continued for ms milliseconds instead of a new search.
+fn ai_ponder(py: Python, uid: String, b: String, ms: i64, threads: usize) -> PyResult<String> {
+    let (b, p) = try!(decode_game(py, b));
+    let d = dame(b, p);
+    Ok(py.allow_threads(move || {
+        let pondered = match take_ponder(&uid) {
+            Some((ai, p)) => if ai == d.ai && p.is_hit(&d) {
+                let predicted = p.predicted();
+                p.reply(&predicted, ms)
+            } else {
+                p.stop();
+                None
+            },
+            None => None
+        };
+        let (m, x) = match pondered {
+            Some(r) => r,
+            None => {
+                let mut x = timed_search(ms, threads);
+                match x.minimax(d.clone()) {
+                    Some(m) => (m, x),
+                    None => return d.positions.to_json(&d.b)
+                }
+            }
+        };
+        let g = d.set(m);
+        if let Some(p) = Ponder::start(&g, &x.path(), timed_search(PONDER_MS, threads)) {
+            let older = std::mem::replace(&mut *PONDERING.lock().unwrap(), vec![(uid, d.ai, p)]);
+            for (_, _, p) in older {
+                p.stop();
+            }
+        }
+        g.positions.to_json(&g.b)
+    }))
+}
+
+// Has to be called with the board after each step or jump of the human in a game in which the
+// AI ponders. When the human's move is complete, the search on the human's time is kept if
+// the human has played the predicted reply and stopped otherwise or if the game is finished.
+// Returns false if there is no search of the game anymore.
+fn ponder_move(py: Python, uid: String, b: String) -> PyResult<bool> {
+    let d = Dame::new(try!(decode_board(py, b)));
+    let p = {
+        let mut v = PONDERING.lock().unwrap();
+        match v.iter().position(|e| e.0 == uid) {
+            Some(i) if d.b.finished() => v.remove(i).2,
+            Some(i) if d.ai != v[i].1 || v[i].2.is_hit(&d) => return Ok(true),
+            Some(i) => v.remove(i).2,
+            None => return Ok(false)
+        }
+    };
+    py.allow_threads(move || p.stop());
+    Ok(false)
+}
+
+// Stops the search on the human's time of the game uid, e.g. if its board is replaced.
+fn ponder_stop(py: Python, uid: String) -> PyResult<bool> {
+    Ok(match take_ponder(&uid) {
+        Some((_, p)) => {
+            py.allow_threads(move || p.stop());
+            true
+        },
+        None => false
+    })
+}
+
+#[derive(RustcEncodable)]
+struct Breakdown {
+    position: Vec<Term>,
+    path: Vec<String>,
+    score: f64,
+    leaf: Vec<Term>,
+}
+
+// Returns the terms of the evaluation of the position and of the leaf at the end of the path
+// found by a search with the given depth as JSON. The terms are from the view of the player
+// to move.
+fn ai_breakdown(py: Python, b: String, depth: usize, threads: usize) -> PyResult<String> {
+    let (b, p) = try!(decode_game(py, b));
+    let d = dame(b, p);
+    let mut x = MiniMax::new(depth);
+    configure(&mut x, threads);
+    x.minimax(d.clone());
+    let path = x.path();
+    json::encode(&Breakdown {
+        position: d.breakdown(),
+        path: path.iter().map(|m| format!("{}", m)).collect(),
+        score: x.score(),
+        leaf: d.leaf_breakdown(&path),
+    }).map_err(|e| PyErr::new::<exc::RuntimeError, _>(py, format!("{}", e)))
 }
 
 // initialize python functions
 
 py_module_initializer!(engine, initengine, PyInit_engine, |py, m| {
     try!(m.add(py, "new_game",   py_fn!(py, new_game())));
+    try!(m.add(py, "from_fen",   py_fn!(py, from_fen(fen: String))));
+    try!(m.add(py, "to_fen",     py_fn!(py, to_fen(b: String))));
     try!(m.add(py, "moves_for",  py_fn!(py, moves_for(b: String, x: i32, y: i32))));
     try!(m.add(py, "move_it",    py_fn!(py, move_it(b: String, x: i32, y: i32, dx: i32, dy: i32))));
+    try!(m.add(py, "play_move",  py_fn!(py, play_move(b: String, m: String))));
+    try!(m.add(py, "setup_position", py_fn!(py, setup_position(p: String))));
     try!(m.add(py, "ai_random",  py_fn!(py, ai_random(b: String))));
-    try!(m.add(py, "ai_minimax", py_fn!(py, ai_minimax(b: String, depth: usize))));
+    try!(m.add(py, "ai_minimax", py_fn!(py, ai_minimax(b: String, depth: usize, threads: usize))));
+    try!(m.add(py, "ai_minimax_time", py_fn!(py, ai_minimax_time(b: String, ms: i64, threads: usize))));
+    try!(m.add(py, "ai_ponder",  py_fn!(py, ai_ponder(uid: String, b: String, ms: i64, threads: usize))));
+    try!(m.add(py, "ponder_move", py_fn!(py, ponder_move(uid: String, b: String))));
+    try!(m.add(py, "ponder_stop", py_fn!(py, ponder_stop(uid: String))));
+    try!(m.add(py, "ai_breakdown", py_fn!(py, ai_breakdown(b: String, depth: usize, threads: usize))));
     Ok(())
 });
@@ -0,0 +1,125 @@
+extern crate engine;
+extern crate rand;
+
+mod reference;
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use engine::board::board::{Board, Color, Player};
+use engine::board::history::Positions;
+use engine::board::position::Position;
+
+use reference::{Game, Piece};
+
+// Differential tests of the move generator: random games are played on a Board and on the
+// reference implementation of the rules in tests/reference, and the valid moves, the pieces,
+// the promotions and the outcome are compared after every single step or jump. Each game has
+// its own seed which is printed if it fails.
+
+const GAMES: u32 = 200;
+const MAX_PLIES: usize = 400;
+
+fn sorted(mut v: Vec<(i32, i32, i32, i32)>) -> Vec<(i32, i32, i32, i32)> {
+    v.sort();
+    v
+}
+
+fn compare(b: &Board, g: &Game, seed: u32, ply: usize) {
+    let at = format!("seed {}, ply {}:\n{}", seed, ply, b);
+    for x in 0..8 {
+        for y in 0..8 {
+            assert_eq!(b.color(x, y).unwrap(), reference::color(g.squares[x as usize][y as usize]), "square ({}, {}), {}", x, y, at);
+        }
+    }
+    assert_eq!(b.winner(), g.winner, "{}", at);
+    assert_eq!(b.finished(), g.finished(), "{}", at);
+    if !g.finished() {
+        assert_eq!(b.player(), g.player, "{}", at);
+        assert_eq!(sorted(b.valid_moves()), sorted(g.moves()), "{}", at);
+    }
+}
+
+// Plays a random game from b and compares each ply with the reference.
+fn play(mut b: Board, rng: &mut XorShiftRng, seed: u32) {
+    b.set_move_rule(reference::MOVE_RULE);
+    let mut squares = [[None; 8]; 8];
+    for x in 0..8 {
+        for y in 0..8 {
+            squares[x][y] = reference::piece(b.color(x as i32, y as i32).unwrap());
+        }
+    }
+    let mut g = Game::new(squares, b.player());
+    let mut positions = Positions::new(&b);
+    compare(&b, &g, seed, 0);
+    for ply in 1..MAX_PLIES {
+        if g.finished() {
+            break;
+        }
+        let m = g.moves();
+        let (x, y, dx, dy) = m[rng.gen_range(0, m.len())];
+        let r = b.try_move(x, y, dx, dy).unwrap_or_else(|e| panic!("seed {}, ply {}: {}", seed, ply, e));
+        let (captured, promoted, turn_over) = g.play(x, y, dx, dy);
+        assert_eq!((r.captured, r.promoted, r.turn_over), (captured, promoted, turn_over), "seed {}, ply {}", seed, ply);
+        if turn_over {
+            positions.push(&mut b);
+        }
+        compare(&b, &g, seed, ply);
+    }
+}
+
+// Returns a random valid position with up to 12 pieces of each color and kings, so that
+// captures in all directions and multi-jumps are more frequent than in games from the start.
+fn random_position(rng: &mut XorShiftRng) -> Board {
+    loop {
+        let mut p = Position::new();
+        for &player in &[Player::White, Player::Black] {
+            for _ in 0..rng.gen_range(1, 13) {
+                let (x, y) = (rng.gen_range(0, 8), rng.gen_range(0, 8));
+                let king = rng.gen_weighted_bool(3);
+                let piece = Piece { player: player, king: king };
+                let crowning = if player == Player::White { 7 } else { 0 };
+                if (x + y) % 2 == 0 && (king || y != crowning) {
+                    p.put(x, y, reference::color(Some(piece))).unwrap();
+                }
+            }
+        }
+        p.set_player(if rng.gen() { Player::White } else { Player::Black });
+        if let Ok(b) = p.build() {
+            return b;
+        }
+    }
+}
+
+fn rng(seed: u32) -> XorShiftRng {
+    XorShiftRng::from_seed([seed, 0x193a_6754, 0xa8a7_d469, 0x9783_0e05])
+}
+
+#[test]
+fn start_position() {
+    for seed in 1..GAMES + 1 {
+        play(Board::new(), &mut rng(seed), seed);
+    }
+}
+
+#[test]
+fn random_positions() {
+    for seed in 1..GAMES + 1 {
+        let mut r = rng(seed);
+        let b = random_position(&mut r);
+        play(b, &mut r, seed);
+    }
+}
+
+#[test]
+fn reference() {
+    // The reference itself: a man which is crowned by a jump ends the turn.
+    let mut squares = [[None; 8]; 8];
+    squares[1][5] = Some(Piece { player: Player::White, king: false });
+    squares[2][6] = Some(Piece { player: Player::Black, king: false });
+    squares[4][6] = Some(Piece { player: Player::Black, king: false });
+    let mut g = Game::new(squares, Player::White);
+    assert_eq!(g.moves(), vec![(1, 5, 3, 7)]);
+    assert_eq!(g.play(1, 5, 3, 7), (Some((2, 6)), true, true));
+    assert_eq!(reference::color(g.squares[3][7]), Color::WhiteDame);
+    assert_eq!(g.player, Player::Black);
+}
@@ -0,0 +1,194 @@
+// A reference implementation of the rules which is deliberately simple: the board is an array
+// of 8 x 8 squares, the moves are found by looking at the neighbouring squares of each piece
+// and nothing is computed incrementally. It is only used to check the engine.
+
+use engine::board::board::{Color, Player};
+
+// Number of moves of each player without a capture and without a move of a man after which
+// the game is a draw, see Board::set_move_rule.
+pub const MOVE_RULE: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Piece {
+    pub player: Player,
+    pub king: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Game {
+    pub squares: [[Option<Piece>; 8]; 8],       // indexed by [x][y]
+    pub player: Player,
+    pub jumping: Option<(i32, i32)>,
+    pub winner: Player,
+    pub draw: bool,
+    history: Vec<([[Option<Piece>; 8]; 8], Player)>,
+    reversible: usize,
+    irreversible: bool,
+}
+
+pub fn color(p: Option<Piece>) -> Color {
+    match p {
+        None => Color::Empty,
+        Some(Piece { player: Player::White, king: false }) => Color::WhiteNormal,
+        Some(Piece { player: Player::White, king: true }) => Color::WhiteDame,
+        Some(Piece { player: _, king: false }) => Color::BlackNormal,
+        Some(Piece { player: _, king: true }) => Color::BlackDame,
+    }
+}
+
+pub fn piece(c: Color) -> Option<Piece> {
+    match c {
+        Color::Empty => None,
+        Color::WhiteNormal => Some(Piece { player: Player::White, king: false }),
+        Color::WhiteDame => Some(Piece { player: Player::White, king: true }),
+        Color::BlackNormal => Some(Piece { player: Player::Black, king: false }),
+        Color::BlackDame => Some(Piece { player: Player::Black, king: true }),
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == Player::White { Player::Black } else { Player::White }
+}
+
+fn inside(x: i32, y: i32) -> bool {
+    x >= 0 && x < 8 && y >= 0 && y < 8
+}
+
+// The row on which the men of player p are crowned.
+fn crowning_row(p: Player) -> i32 {
+    if p == Player::White { 7 } else { 0 }
+}
+
+impl Game {
+    // Creates a game from the squares indexed by [x][y] with player p to move.
+    pub fn new(squares: [[Option<Piece>; 8]; 8], p: Player) -> Game {
+        let mut g = Game {
+            squares: squares,
+            player: p,
+            jumping: None,
+            winner: Player::None,
+            draw: false,
+            history: vec![(squares, p)],
+            reversible: 0,
+            irreversible: false,
+        };
+        if g.moves().is_empty() {
+            g.winner = opponent(p);
+        }
+        g
+    }
+
+    pub fn finished(&self) -> bool {
+        self.winner != Player::None || self.draw
+    }
+
+    fn at(&self, x: i32, y: i32) -> Option<Piece> {
+        if inside(x, y) { self.squares[x as usize][y as usize] } else { None }
+    }
+
+    fn empty(&self, x: i32, y: i32) -> bool {
+        inside(x, y) && self.squares[x as usize][y as usize].is_none()
+    }
+
+    // The diagonal directions in which the piece on (x, y) may move.
+    fn directions(&self, x: i32, y: i32) -> Vec<(i32, i32)> {
+        match self.at(x, y) {
+            Some(Piece { king: true, .. }) => vec![(1, 1), (-1, 1), (1, -1), (-1, -1)],
+            Some(Piece { player: Player::White, .. }) => vec![(1, 1), (-1, 1)],
+            Some(_) => vec![(1, -1), (-1, -1)],
+            None => vec![]
+        }
+    }
+
+    fn jumps(&self, x: i32, y: i32) -> Vec<(i32, i32, i32, i32)> {
+        self.directions(x, y).into_iter().filter(|&(dx, dy)| {
+            match self.at(x + dx, y + dy) {
+                Some(p) => p.player == opponent(self.player) && self.empty(x + 2 * dx, y + 2 * dy),
+                None => false
+            }
+        }).map(|(dx, dy)| (x, y, x + 2 * dx, y + 2 * dy)).collect()
+    }
+
+    fn steps(&self, x: i32, y: i32) -> Vec<(i32, i32, i32, i32)> {
+        self.directions(x, y).into_iter()
+            .filter(|&(dx, dy)| self.empty(x + dx, y + dy))
+            .map(|(dx, dy)| (x, y, x + dx, y + dy))
+            .collect()
+    }
+
+    // Returns the valid single steps and jumps as (x, y, destination x, destination y).
+    pub fn moves(&self) -> Vec<(i32, i32, i32, i32)> {
+        if self.finished() {
+            return vec![];
+        }
+        if let Some((x, y)) = self.jumping {
+            return self.jumps(x, y);
+        }
+        let mut own = vec![];
+        for x in 0..8 {
+            for y in 0..8 {
+                if let Some(p) = self.at(x, y) {
+                    if p.player == self.player {
+                        own.push((x, y));
+                    }
+                }
+            }
+        }
+        let jumps: Vec<_> = own.iter().flat_map(|&(x, y)| self.jumps(x, y)).collect();
+        if !jumps.is_empty() {
+            return jumps;
+        }
+        own.iter().flat_map(|&(x, y)| self.steps(x, y)).collect()
+    }
+
+    // Makes a valid single step or jump and returns the captured square, whether the piece has
+    // been crowned and whether the turn is over.
+    pub fn play(&mut self, x: i32, y: i32, dx: i32, dy: i32) -> (Option<(i32, i32)>, bool, bool) {
+        assert!(self.moves().contains(&(x, y, dx, dy)));
+        let mut p = self.at(x, y).unwrap();
+        self.squares[x as usize][y as usize] = None;
+        let captured = if (dx - x).abs() == 2 {
+            let c = ((x + dx) / 2, (y + dy) / 2);
+            self.squares[c.0 as usize][c.1 as usize] = None;
+            Some(c)
+        } else {
+            None
+        };
+        let crowned = !p.king && dy == crowning_row(p.player);
+        if crowned {
+            p.king = true;
+        }
+        self.squares[dx as usize][dy as usize] = Some(p);
+        if captured.is_some() || !p.king || crowned {
+            self.irreversible = true;
+        }
+
+        // A jump is continued with the same piece unless it has just been crowned.
+        self.jumping = None;
+        if captured.is_some() && !crowned && !self.jumps(dx, dy).is_empty() {
+            self.jumping = Some((dx, dy));
+            return (captured, crowned, false);
+        }
+
+        let mover = self.player;
+        self.player = opponent(mover);
+        if self.irreversible {
+            self.history = vec![];
+            self.reversible = 0;
+        } else {
+            self.reversible += 1;
+        }
+        self.irreversible = false;
+        self.history.push((self.squares, self.player));
+
+        let current = (self.squares, self.player);
+        if self.moves().is_empty() {
+            self.winner = mover;
+        } else {
+            // The move rule or a position which has occurred three times.
+            self.draw = self.reversible >= 2 * MOVE_RULE ||
+                self.history.iter().filter(|&h| *h == current).count() >= 3;
+        }
+        (captured, crowned, true)
+    }
+}